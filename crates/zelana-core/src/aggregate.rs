@@ -0,0 +1,214 @@
+use {
+    crate::{
+        crypto::to_bytes,
+        merkle::list_root,
+        prover::{check_version, BatchPublicValues, DepositRange, PublicValuesError},
+    },
+    thiserror::Error,
+    wincode::{SchemaRead, SchemaWrite},
+};
+
+/// The input fed into the aggregation Guest, followed on SP1 stdin by one proof per batch.
+#[derive(SchemaRead, SchemaWrite, Debug, Clone)]
+pub struct AggregateInput {
+    /// Verifying key digest (`HashableKey::hash_u32`) of the batch program every
+    /// aggregated proof must come from.
+    pub batch_program: [u32; 8],
+    /// The public values each batch proof commits, in batch order.
+    pub batches: Vec<Vec<u8>>,
+}
+
+/// What the aggregation Guest commits: a single statement for a run of consecutive batches.
+#[derive(SchemaRead, SchemaWrite, Debug, Clone, PartialEq, Eq)]
+pub struct AggregatePublicValues {
+    /// Layout version (`AggregatePublicValues::VERSION`). Always encoded first.
+    pub version: u16,
+    /// The batch program the aggregated proofs were verified against.
+    pub batch_program: [u32; 8],
+    pub chain_id: u64,
+    pub first_batch_height: u64,
+    pub last_batch_height: u64,
+    /// `l1_seq` of the last deposit applied before the first batch.
+    pub prev_l1_seq: Option<u64>,
+    /// Pre-state root of the first batch.
+    pub pre_state_root: [u8; 32],
+    /// Post-state root of the last batch.
+    pub post_state_root: [u8; 32],
+    /// The L1 deposits applied by all batches; `None` if they have none.
+    pub deposits: Option<DepositRange>,
+    /// `list_root` over the batches' `withdrawals_root`s, in batch order. A withdrawal is
+    /// claimed with its batch's `ExitProof` plus a `ListProof` of that batch's root at
+    /// `batch_height - first_batch_height`.
+    pub withdrawals_root: [u8; 32],
+}
+
+/// Why a run of batches can't be aggregated.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum AggregateError {
+    #[error("No batches to aggregate")]
+    Empty,
+    #[error("Batch {batch_height} is for another chain")]
+    WrongChain { batch_height: u64 },
+    #[error("Batch {batch_height} does not follow the previous batch")]
+    NotConsecutive { batch_height: u64 },
+    #[error("Batch {batch_height} does not start from the previous post-state root")]
+    RootMismatch { batch_height: u64 },
+    #[error("Batch {batch_height} does not continue the previous batch's deposits")]
+    DepositGap { batch_height: u64 },
+}
+
+impl AggregatePublicValues {
+    pub const VERSION: u16 = 2;
+
+    /// Chains `batches` into one statement. They must be consecutive batches of one
+    /// chain, each starting from the post-state root and the last deposit of the one before.
+    pub fn new(
+        batch_program: [u32; 8],
+        batches: &[BatchPublicValues],
+    ) -> Result<Self, AggregateError> {
+        let (first, last) = match batches {
+            [first, .., last] => (first, last),
+            [only] => (only, only),
+            [] => return Err(AggregateError::Empty),
+        };
+
+        for pair in batches.windows(2) {
+            let (prev, next) = (&pair[0], &pair[1]);
+            if next.chain_id != first.chain_id {
+                return Err(AggregateError::WrongChain {
+                    batch_height: next.batch_height,
+                });
+            }
+            if next.batch_height != prev.batch_height + 1 {
+                return Err(AggregateError::NotConsecutive {
+                    batch_height: next.batch_height,
+                });
+            }
+            if next.pre_state_root != prev.post_state_root {
+                return Err(AggregateError::RootMismatch {
+                    batch_height: next.batch_height,
+                });
+            }
+            if next.prev_l1_seq != prev.last_l1_seq() {
+                return Err(AggregateError::DepositGap {
+                    batch_height: next.batch_height,
+                });
+            }
+        }
+
+        let mut ranges = batches.iter().filter_map(|batch| batch.deposits);
+        let deposits = ranges.next().map(|first| DepositRange {
+            first_l1_seq: first.first_l1_seq,
+            last_l1_seq: ranges.last().unwrap_or(first).last_l1_seq,
+        });
+        let withdrawal_roots: Vec<[u8; 32]> =
+            batches.iter().map(|batch| batch.withdrawals_root).collect();
+
+        Ok(Self {
+            version: Self::VERSION,
+            batch_program,
+            chain_id: first.chain_id,
+            first_batch_height: first.batch_height,
+            last_batch_height: last.batch_height,
+            prev_l1_seq: first.prev_l1_seq,
+            pre_state_root: first.pre_state_root,
+            post_state_root: last.post_state_root,
+            deposits,
+            withdrawals_root: list_root(&withdrawal_roots),
+        })
+    }
+
+    /// The bytes the aggregation Guest commits.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        to_bytes(self)
+    }
+
+    /// Decodes committed public values, rejecting versions this build doesn't know.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PublicValuesError> {
+        check_version(bytes, Self::VERSION)?;
+        wincode::deserialize(bytes).map_err(|_| PublicValuesError::Malformed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::merkle::EMPTY_HASH};
+
+    // Batches without deposits continue from l1_seq 12
+    fn batch(height: u64, pre: u8, post: u8, deposits: Option<(u64, u64)>) -> BatchPublicValues {
+        BatchPublicValues {
+            version: BatchPublicValues::VERSION,
+            batch_height: height,
+            chain_id: 1,
+            prev_l1_seq: deposits.map(|(first, _)| first - 1).or(Some(12)),
+            pre_state_root: [pre; 32],
+            post_state_root: [post; 32],
+            transactions_root: EMPTY_HASH,
+            deposits: deposits.map(|(first, last)| DepositRange {
+                first_l1_seq: first,
+                last_l1_seq: last,
+            }),
+            withdrawals_root: [height as u8; 32],
+        }
+    }
+
+    #[test]
+    fn test_aggregate_chains_consecutive_batches() {
+        let batches = vec![
+            batch(4, 1, 2, Some((10, 12))),
+            batch(5, 2, 3, None),
+            batch(6, 3, 4, Some((13, 13))),
+        ];
+        let values = AggregatePublicValues::new([9u32; 8], &batches).unwrap();
+        assert_eq!(
+            (values.first_batch_height, values.last_batch_height),
+            (4, 6)
+        );
+        assert_eq!(values.pre_state_root, [1u8; 32]);
+        assert_eq!(values.post_state_root, [4u8; 32]);
+        assert_eq!(
+            values.deposits,
+            Some(DepositRange {
+                first_l1_seq: 10,
+                last_l1_seq: 13
+            })
+        );
+        assert_eq!(
+            values.withdrawals_root,
+            list_root(&[[4u8; 32], [5u8; 32], [6u8; 32]])
+        );
+        assert_eq!(
+            AggregatePublicValues::from_bytes(&values.to_bytes()),
+            Ok(values)
+        );
+
+        assert_eq!(
+            AggregatePublicValues::new([9u32; 8], &[]),
+            Err(AggregateError::Empty)
+        );
+        let gap = vec![batch(4, 1, 2, None), batch(6, 2, 3, None)];
+        assert_eq!(
+            AggregatePublicValues::new([9u32; 8], &gap),
+            Err(AggregateError::NotConsecutive { batch_height: 6 })
+        );
+        let forked = vec![batch(4, 1, 2, None), batch(5, 7, 3, None)];
+        assert_eq!(
+            AggregatePublicValues::new([9u32; 8], &forked),
+            Err(AggregateError::RootMismatch { batch_height: 5 })
+        );
+        let mut other_chain = batch(5, 2, 3, None);
+        other_chain.chain_id = 2;
+        assert_eq!(
+            AggregatePublicValues::new([9u32; 8], &[batch(4, 1, 2, None), other_chain]),
+            Err(AggregateError::WrongChain { batch_height: 5 })
+        );
+        let skipped = vec![
+            batch(4, 1, 2, Some((10, 12))),
+            batch(5, 2, 3, Some((14, 14))),
+        ];
+        assert_eq!(
+            AggregatePublicValues::new([9u32; 8], &skipped),
+            Err(AggregateError::DepositGap { batch_height: 5 })
+        );
+    }
+}
@@ -0,0 +1,221 @@
+use {
+    crate::{
+        crypto::to_bytes,
+        identity::{AccountId, IdentityKeys},
+        transaction::{
+            L2Transaction, SignedTransaction, TransactionData, WithdrawData, WithdrawRequest,
+        },
+    },
+    ed25519_dalek::{Signature, Verifier, VerifyingKey},
+    thiserror::Error,
+};
+
+/// Prefix of the message signed for a transfer.
+pub const TRANSFER_DOMAIN: &[u8] = b"zelana:transfer:v1";
+/// Prefix of the message signed for a withdrawal. Transfers and withdrawals encode to
+/// the same bytes, so without distinct prefixes one could be replayed as the other.
+pub const WITHDRAW_DOMAIN: &[u8] = b"zelana:withdraw:v1";
+
+fn domain_message(domain: &[u8], payload: Vec<u8>) -> Vec<u8> {
+    let mut msg = domain.to_vec();
+    msg.extend(payload);
+    msg
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum AuthError {
+    #[error("Invalid signature")]
+    BadSignature,
+    #[error("Signer does not own {0:?}")]
+    NotOwner(AccountId),
+}
+
+/// Whether `signer_pk` controls `account`.
+///
+/// With a privacy key the account must be `IdentityKeys::derive_id` of both keys.
+/// Without one the account id must be the signer key itself, as for accounts
+/// mapped from an L1 (Solana) key.
+pub fn owns(account: &AccountId, signer_pk: &[u8; 32], privacy_pk: Option<&[u8; 32]>) -> bool {
+    match privacy_pk {
+        Some(privacy_pk) => {
+            let keys = IdentityKeys {
+                signer_pk: *signer_pk,
+                privacy_pk: *privacy_pk,
+            };
+            keys.derive_id() == *account
+        }
+        None => account.0 == *signer_pk,
+    }
+}
+
+/// Checks an Ed25519 signature over `msg`.
+pub fn verify_signature(
+    signer_pk: &[u8; 32],
+    msg: &[u8],
+    signature: &[u8],
+) -> Result<(), AuthError> {
+    let vk = VerifyingKey::from_bytes(signer_pk).map_err(|_| AuthError::BadSignature)?;
+    let sig = Signature::from_slice(signature).map_err(|_| AuthError::BadSignature)?;
+    vk.verify(msg, &sig).map_err(|_| AuthError::BadSignature)
+}
+
+fn authorize(
+    account: &AccountId,
+    signer_pk: &[u8; 32],
+    privacy_pk: Option<&[u8; 32]>,
+    msg: &[u8],
+    signature: &[u8],
+) -> Result<(), AuthError> {
+    verify_signature(signer_pk, msg, signature)?;
+    if !owns(account, signer_pk, privacy_pk) {
+        return Err(AuthError::NotOwner(*account));
+    }
+    Ok(())
+}
+
+impl TransactionData {
+    /// The canonical bytes the signer signs: `TRANSFER_DOMAIN` then the serialized data.
+    pub fn signing_message(&self) -> Vec<u8> {
+        domain_message(TRANSFER_DOMAIN, to_bytes(self))
+    }
+}
+
+impl WithdrawData {
+    /// The canonical bytes the signer signs: `WITHDRAW_DOMAIN` then the serialized data.
+    pub fn signing_message(&self) -> Vec<u8> {
+        domain_message(WITHDRAW_DOMAIN, to_bytes(self))
+    }
+}
+
+impl SignedTransaction {
+    pub fn signing_message(&self) -> Vec<u8> {
+        self.data.signing_message()
+    }
+
+    /// Checks the signature and that the signer owns `data.from`.
+    pub fn verify(&self) -> Result<(), AuthError> {
+        authorize(
+            &self.data.from,
+            &self.signer_pubkey,
+            self.privacy_pubkey.as_ref(),
+            &self.signing_message(),
+            &self.signature,
+        )
+    }
+}
+
+impl WithdrawRequest {
+    pub fn signing_message(&self) -> Vec<u8> {
+        self.data.signing_message()
+    }
+
+    /// Checks the signature and that the signer owns `data.from`.
+    pub fn verify(&self) -> Result<(), AuthError> {
+        authorize(
+            &self.data.from,
+            &self.signer_pubkey,
+            self.privacy_pubkey.as_ref(),
+            &self.signing_message(),
+            &self.signature,
+        )
+    }
+}
+
+impl L2Transaction {
+    /// Checks that the transaction was authorized by the owner of the debited account.
+    /// Deposits carry no signature; they are authorized by the L1 bridge.
+    pub fn verify_authorization(&self) -> Result<(), AuthError> {
+        match self {
+            L2Transaction::Transfer(tx) => tx.verify(),
+            L2Transaction::Withdraw(req) => req.verify(),
+            L2Transaction::Deposit(_) => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        ed25519_dalek::{Signer, SigningKey},
+    };
+
+    fn sign(key: &SigningKey, from: AccountId, privacy_pk: Option<[u8; 32]>) -> SignedTransaction {
+        let data = TransactionData {
+            from,
+            to: AccountId([9u8; 32]),
+            amount: 5,
+            nonce: 0,
+            chain_id: 1,
+        };
+        let signature = key.sign(&data.signing_message()).to_bytes().to_vec();
+        SignedTransaction {
+            data,
+            signature,
+            signer_pubkey: key.verifying_key().to_bytes(),
+            privacy_pubkey: privacy_pk,
+        }
+    }
+
+    #[test]
+    fn test_signer_must_own_sender() {
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let privacy_pk = [3u8; 32];
+        let owned = IdentityKeys {
+            signer_pk: key.verifying_key().to_bytes(),
+            privacy_pk,
+        }
+        .derive_id();
+
+        // Dual-key account
+        assert_eq!(sign(&key, owned, Some(privacy_pk)).verify(), Ok(()));
+        // L1-mapped account whose id is the signer key
+        let l1_id = AccountId(key.verifying_key().to_bytes());
+        assert_eq!(sign(&key, l1_id, None).verify(), Ok(()));
+
+        // A valid signature over someone else's account
+        let victim = AccountId([1u8; 32]);
+        assert_eq!(
+            sign(&key, victim, Some(privacy_pk)).verify(),
+            Err(AuthError::NotOwner(victim))
+        );
+        assert_eq!(
+            sign(&key, victim, None).verify(),
+            Err(AuthError::NotOwner(victim))
+        );
+    }
+
+    #[test]
+    fn test_tampered_transaction_is_rejected() {
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let l1_id = AccountId(key.verifying_key().to_bytes());
+
+        let mut tx = sign(&key, l1_id, None);
+        tx.data.amount += 1;
+        assert_eq!(tx.verify(), Err(AuthError::BadSignature));
+    }
+
+    #[test]
+    fn test_transfer_signature_is_not_a_withdrawal() {
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let l1_id = AccountId(key.verifying_key().to_bytes());
+        let tx = sign(&key, l1_id, None);
+
+        // Same fields, so the same encoding; only the domain tells them apart
+        let rewrapped = WithdrawRequest {
+            data: WithdrawData {
+                from: tx.data.from,
+                to_l1_address: tx.data.to.0,
+                amount: tx.data.amount,
+                nonce: tx.data.nonce,
+                chain_id: tx.data.chain_id,
+            },
+            signature: tx.signature.clone(),
+            signer_pubkey: tx.signer_pubkey,
+            privacy_pubkey: tx.privacy_pubkey,
+        };
+        assert_eq!(to_bytes(&rewrapped.data), to_bytes(&tx.data));
+        assert_eq!(tx.verify(), Ok(()));
+        assert_eq!(rewrapped.verify(), Err(AuthError::BadSignature));
+    }
+}
@@ -0,0 +1,191 @@
+use {
+    crate::{merkle::list_root, prover::BatchPublicValues, transaction::L2Transaction},
+    blake3::Hasher,
+    wincode::{SchemaRead, SchemaWrite},
+};
+
+// Domain separation so a header hash can't collide with any tree hash.
+const HEADER_PREFIX: &[u8] = b"zelana:batch-header:v1";
+
+/// The header of an L2 batch (block).
+/// Headers link into a chain through `parent_hash`.
+#[derive(Debug, Clone, PartialEq, Eq, SchemaRead, SchemaWrite)]
+pub struct BatchHeader {
+    /// Height of this batch. The first batch has height 1.
+    pub height: u64,
+    /// `hash()` of the previous header; `GENESIS_PARENT` for the first batch.
+    pub parent_hash: [u8; 32],
+    /// `list_root` over the hashes of all transactions, in execution order.
+    pub transactions_root: [u8; 32],
+    pub pre_state_root: [u8; 32],
+    pub post_state_root: [u8; 32],
+    /// `list_root` over the hashes of the batch's deposits.
+    pub deposits_root: [u8; 32],
+    /// `withdrawal::withdrawals_root` over the batch's executed withdrawals; what the L1
+    /// bridge pays out against.
+    pub withdrawals_root: [u8; 32],
+    /// `list_root` over the hashes of the transaction receipts.
+    pub receipts_root: [u8; 32],
+    /// Unix time (seconds) the batch was sealed.
+    pub timestamp: u64,
+}
+
+impl BatchHeader {
+    /// Parent hash of the first batch.
+    pub const GENESIS_PARENT: [u8; 32] = [0u8; 32];
+
+    /// Builds the header of the batch `values` describes, so that the header and the
+    /// proof's public values agree on height, state roots and transaction and withdrawal
+    /// roots. The deposits root is derived from `transactions`.
+    pub fn new(
+        parent_hash: [u8; 32],
+        transactions: &[L2Transaction],
+        values: &BatchPublicValues,
+        receipts_root: [u8; 32],
+        timestamp: u64,
+    ) -> Self {
+        Self {
+            height: values.batch_height,
+            parent_hash,
+            transactions_root: values.transactions_root,
+            pre_state_root: values.pre_state_root,
+            post_state_root: values.post_state_root,
+            deposits_root: deposits_root(transactions),
+            withdrawals_root: values.withdrawals_root,
+            receipts_root,
+            timestamp,
+        }
+    }
+
+    /// The canonical header hash.
+    /// Logic: Hash( Prefix || Height || Parent || TxRoot || PreRoot || PostRoot
+    ///              || DepositsRoot || WithdrawalsRoot || ReceiptsRoot || Timestamp ),
+    /// integers little-endian.
+    pub fn hash(&self) -> [u8; 32] {
+        let mut hasher = Hasher::new();
+        hasher.update(HEADER_PREFIX);
+        hasher.update(&self.height.to_le_bytes());
+        hasher.update(&self.parent_hash);
+        hasher.update(&self.transactions_root);
+        hasher.update(&self.pre_state_root);
+        hasher.update(&self.post_state_root);
+        hasher.update(&self.deposits_root);
+        hasher.update(&self.withdrawals_root);
+        hasher.update(&self.receipts_root);
+        hasher.update(&self.timestamp.to_le_bytes());
+        hasher.finalize().into()
+    }
+
+    /// Whether `self` directly follows `parent` in the chain.
+    pub fn extends(&self, parent: &BatchHeader) -> bool {
+        self.height == parent.height + 1
+            && self.parent_hash == parent.hash()
+            && self.pre_state_root == parent.post_state_root
+    }
+}
+
+pub fn transactions_root(transactions: &[L2Transaction]) -> [u8; 32] {
+    let hashes: Vec<[u8; 32]> = transactions.iter().map(L2Transaction::hash).collect();
+    list_root(&hashes)
+}
+
+pub fn deposits_root(transactions: &[L2Transaction]) -> [u8; 32] {
+    let hashes: Vec<[u8; 32]> = transactions
+        .iter()
+        .filter(|tx| matches!(tx, L2Transaction::Deposit(_)))
+        .map(L2Transaction::hash)
+        .collect();
+    list_root(&hashes)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            merkle::{MultiProof, EMPTY_HASH},
+            AccountId, BatchInput, DepositEvent,
+        },
+    };
+
+    fn deposit(seq: u64) -> L2Transaction {
+        L2Transaction::Deposit(DepositEvent {
+            to: AccountId([1u8; 32]),
+            amount: 10,
+            l1_seq: seq,
+        })
+    }
+
+    fn header(
+        height: u64,
+        parent_hash: [u8; 32],
+        transactions: Vec<L2Transaction>,
+        pre_state_root: [u8; 32],
+        post_state_root: [u8; 32],
+        timestamp: u64,
+    ) -> BatchHeader {
+        let input = BatchInput {
+            batch_height: height,
+            chain_id: 1,
+            prev_l1_seq: None,
+            pre_state_root,
+            transactions,
+            witness_accounts: vec![],
+            witness_proof: MultiProof { nodes: vec![] },
+        };
+        let values = BatchPublicValues::new(&input, post_state_root, &[]);
+        BatchHeader::new(
+            parent_hash,
+            &input.transactions,
+            &values,
+            EMPTY_HASH,
+            timestamp,
+        )
+    }
+
+    #[test]
+    fn test_header_chain() {
+        let first = header(
+            1,
+            BatchHeader::GENESIS_PARENT,
+            vec![deposit(1), deposit(2)],
+            [1; 32],
+            [2; 32],
+            100,
+        );
+        assert_eq!(first.deposits_root, first.transactions_root);
+        assert_eq!(first.withdrawals_root, EMPTY_HASH);
+
+        let second = header(2, first.hash(), vec![], [2; 32], [2; 32], 110);
+        assert!(second.extends(&first));
+        assert_eq!(second.transactions_root, EMPTY_HASH);
+
+        // Every field is bound by the hash
+        let mut tampered = first.clone();
+        tampered.timestamp += 1;
+        assert_ne!(tampered.hash(), first.hash());
+        assert!(!second.extends(&tampered));
+    }
+
+    #[test]
+    fn test_transaction_order_matters() {
+        let a = header(
+            1,
+            [0; 32],
+            vec![deposit(1), deposit(2)],
+            [0; 32],
+            [0; 32],
+            0,
+        );
+        let b = header(
+            1,
+            [0; 32],
+            vec![deposit(2), deposit(1)],
+            [0; 32],
+            [0; 32],
+            0,
+        );
+        assert_ne!(a.transactions_root, b.transactions_root);
+        assert_ne!(a.hash(), b.hash());
+    }
+}
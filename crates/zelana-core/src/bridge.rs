@@ -0,0 +1,130 @@
+use {
+    crate::{identity::AccountId, transaction::DepositEvent},
+    thiserror::Error,
+};
+
+/// The `l1_seq` of the first deposit the bridge emits. Each later deposit carries the
+/// next one, with no gaps, and the rollup applies deposits in that order.
+pub const FIRST_L1_SEQ: u64 = 0;
+
+/// An event the L1 bridge program emits with `sol_log_data`.
+///
+/// Encoded as `version: u8`, `kind: u8` and a fixed little-endian payload, so it
+/// can be written on-chain without a serializer. Decoders for older versions stay
+/// around as long as the bridge may have emitted them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BridgeEvent {
+    Deposit(DepositEvent),
+}
+
+/// Why bytes emitted as event data are not a `BridgeEvent`.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum BridgeEventError {
+    #[error("Empty event data")]
+    Empty,
+    #[error("Unsupported bridge event version {0}")]
+    UnsupportedVersion(u8),
+    #[error("Unknown bridge event kind {0}")]
+    UnknownKind(u8),
+    #[error("Bridge event kind {kind} has {len} payload bytes, expected {expected}")]
+    BadLength {
+        kind: u8,
+        len: usize,
+        expected: usize,
+    },
+}
+
+impl BridgeEvent {
+    pub const VERSION: u8 = 1;
+
+    const KIND_DEPOSIT: u8 = 0;
+    /// `to` (32 bytes), `amount` (u64) and `l1_seq` (u64).
+    const DEPOSIT_V1_LEN: usize = 48;
+
+    /// The event data the bridge emits.
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            BridgeEvent::Deposit(event) => {
+                let mut data = Vec::with_capacity(2 + Self::DEPOSIT_V1_LEN);
+                data.extend([Self::VERSION, Self::KIND_DEPOSIT]);
+                data.extend(event.to.0);
+                data.extend(event.amount.to_le_bytes());
+                data.extend(event.l1_seq.to_le_bytes());
+                data
+            }
+        }
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, BridgeEventError> {
+        match data {
+            [] => Err(BridgeEventError::Empty),
+            [1, rest @ ..] => Self::decode_v1(rest),
+            [version, ..] => Err(BridgeEventError::UnsupportedVersion(*version)),
+        }
+    }
+
+    fn decode_v1(data: &[u8]) -> Result<Self, BridgeEventError> {
+        let Some((&kind, payload)) = data.split_first() else {
+            return Err(BridgeEventError::Empty);
+        };
+        match kind {
+            Self::KIND_DEPOSIT => {
+                let payload: &[u8; Self::DEPOSIT_V1_LEN] =
+                    payload
+                        .try_into()
+                        .map_err(|_| BridgeEventError::BadLength {
+                            kind,
+                            len: payload.len(),
+                            expected: Self::DEPOSIT_V1_LEN,
+                        })?;
+                let (to, rest) = payload.split_at(32);
+                let (amount, l1_seq) = rest.split_at(8);
+                Ok(BridgeEvent::Deposit(DepositEvent {
+                    to: AccountId(to.try_into().unwrap()),
+                    amount: u64::from_le_bytes(amount.try_into().unwrap()),
+                    l1_seq: u64::from_le_bytes(l1_seq.try_into().unwrap()),
+                }))
+            }
+            other => Err(BridgeEventError::UnknownKind(other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deposit_event_round_trip() {
+        let event = BridgeEvent::Deposit(DepositEvent {
+            to: AccountId([5u8; 32]),
+            amount: 1_000,
+            l1_seq: 42,
+        });
+        let data = event.encode();
+        assert_eq!(data.len(), 50);
+        assert_eq!(BridgeEvent::decode(&data), Ok(event));
+
+        assert_eq!(BridgeEvent::decode(&[]), Err(BridgeEventError::Empty));
+        let mut future = data.clone();
+        future[0] = 2;
+        assert_eq!(
+            BridgeEvent::decode(&future),
+            Err(BridgeEventError::UnsupportedVersion(2))
+        );
+        let mut unknown = data.clone();
+        unknown[1] = 9;
+        assert_eq!(
+            BridgeEvent::decode(&unknown),
+            Err(BridgeEventError::UnknownKind(9))
+        );
+        assert_eq!(
+            BridgeEvent::decode(&data[..49]),
+            Err(BridgeEventError::BadLength {
+                kind: 0,
+                len: 47,
+                expected: 48
+            })
+        );
+    }
+}
@@ -1,10 +1,28 @@
+pub mod aggregate;
+pub mod auth;
+pub mod batch;
+pub mod bridge;
 pub mod crypto;
 pub mod identity;
+pub mod merkle;
 pub mod prover;
+pub mod query;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
 pub mod transaction;
+pub mod withdrawal;
 
 pub use {
+    aggregate::{AggregateInput, AggregatePublicValues},
+    batch::BatchHeader,
+    bridge::{BridgeEvent, BridgeEventError},
     identity::{AccountId, IdentityKeys},
-    prover::{AccountData, BatchInput},
-    transaction::{DepositEvent, L2Transaction, SignedTransaction, TransactionData},
+    merkle::{MerkleProof, MultiProof, SparseMerkleTree},
+    prover::{AccountData, AccountWitness, BatchInput, BatchPublicValues},
+    query::{PendingDeposit, Query, QueryReply},
+    transaction::{
+        DepositEvent, L2Transaction, SignedTransaction, TransactionData, WithdrawData,
+        WithdrawRequest,
+    },
+    withdrawal::ExitProof,
 };
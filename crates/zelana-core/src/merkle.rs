@@ -0,0 +1,900 @@
+use {
+    crate::{AccountData, AccountId},
+    blake3::Hasher,
+    std::{
+        collections::{HashMap, HashSet},
+        convert::Infallible,
+    },
+    thiserror::Error,
+    wincode::{SchemaRead, SchemaWrite},
+};
+
+/// Number of levels in the account tree (one per bit of an `AccountId`).
+pub const TREE_DEPTH: usize = 256;
+
+/// Hash of an empty subtree.
+pub const EMPTY_HASH: [u8; 32] = [0u8; 32];
+
+// Domain separation so a leaf can never be passed off as an internal node.
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+/// Hash of an account leaf.
+/// Logic: Hash( 0x00 || ID || Balance || Nonce )
+pub fn leaf_hash(id: &AccountId, data: &AccountData) -> [u8; 32] {
+    let mut hasher = Hasher::new();
+    hasher.update(&[LEAF_PREFIX]);
+    hasher.update(&id.0);
+    hasher.update(&data.balance.to_le_bytes());
+    hasher.update(&data.nonce.to_le_bytes());
+    hasher.finalize().into()
+}
+
+/// The leaf stored for an account, or `None` for the default state (which is never stored).
+pub fn account_leaf(id: &AccountId, data: &AccountData) -> Option<[u8; 32]> {
+    (!data.is_empty()).then(|| leaf_hash(id, data))
+}
+
+/// Hash of an internal node.
+/// Logic: Hash( 0x01 || Left || Right )
+pub fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Hasher::new();
+    hasher.update(&[NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// Direction taken at `depth` on the way down to `id` (`true` = right).
+/// Bits are read MSB-first.
+pub fn path_bit(id: &AccountId, depth: usize) -> bool {
+    (id.0[depth / 8] >> (7 - depth % 8)) & 1 == 1
+}
+
+/// Position of a node in the tree: its depth plus the path bits leading to it.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct NodeKey {
+    pub depth: u16,
+    /// The first `depth` bits of the path, remaining bits zeroed.
+    pub path: [u8; 32],
+}
+
+impl NodeKey {
+    pub const ROOT: NodeKey = NodeKey {
+        depth: 0,
+        path: [0u8; 32],
+    };
+
+    /// The node at `depth` on the path to `id`.
+    pub fn on_path(id: &AccountId, depth: usize) -> Self {
+        let mut path = [0u8; 32];
+        let full = depth / 8;
+        path[..full].copy_from_slice(&id.0[..full]);
+        let bits = depth % 8;
+        if bits > 0 {
+            path[full] = id.0[full] & (0xFFu8 << (8 - bits));
+        }
+        Self {
+            depth: depth as u16,
+            path,
+        }
+    }
+
+    /// The other child of this node's parent.
+    pub fn sibling(&self) -> Self {
+        debug_assert!(self.depth > 0, "the root has no sibling");
+        let bit = self.depth as usize - 1;
+        let mut path = self.path;
+        path[bit / 8] ^= 1 << (7 - bit % 8);
+        Self {
+            depth: self.depth,
+            path,
+        }
+    }
+
+    /// The child of this node on the given side.
+    pub fn child(&self, right: bool) -> Self {
+        debug_assert!(
+            (self.depth as usize) < TREE_DEPTH,
+            "leaves have no children"
+        );
+        let bit = self.depth as usize;
+        let mut path = self.path;
+        if right {
+            path[bit / 8] |= 1 << (7 - bit % 8);
+        }
+        Self {
+            depth: self.depth + 1,
+            path,
+        }
+    }
+
+    /// Fixed-width encoding for key-value backends: Depth (BE) || Path.
+    pub fn to_bytes(&self) -> [u8; 34] {
+        let mut out = [0u8; 34];
+        out[..2].copy_from_slice(&self.depth.to_be_bytes());
+        out[2..].copy_from_slice(&self.path);
+        out
+    }
+}
+
+/// A stored, non-empty subtree.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, SchemaRead, SchemaWrite)]
+pub enum TreeNode {
+    /// A subtree holding exactly one account.
+    /// Leaves sit at the shallowest depth where they are alone, so its hash is the leaf hash.
+    Leaf(AccountId, [u8; 32]),
+
+    /// A subtree holding two or more accounts.
+    Internal([u8; 32]),
+}
+
+impl TreeNode {
+    pub fn hash(&self) -> [u8; 32] {
+        match self {
+            TreeNode::Leaf(_, hash) | TreeNode::Internal(hash) => *hash,
+        }
+    }
+}
+
+/// Backing storage for tree nodes. Missing keys are empty subtrees.
+/// Lets the sequencer keep nodes on disk while the guest keeps them in memory.
+pub trait NodeStore {
+    type Error;
+
+    fn get_node(&self, key: &NodeKey) -> Result<Option<TreeNode>, Self::Error>;
+
+    /// Store a node, or delete it when `node` is `None`.
+    fn put_node(&mut self, key: NodeKey, node: Option<TreeNode>) -> Result<(), Self::Error>;
+}
+
+impl NodeStore for HashMap<NodeKey, TreeNode> {
+    type Error = Infallible;
+
+    fn get_node(&self, key: &NodeKey) -> Result<Option<TreeNode>, Infallible> {
+        Ok(self.get(key).copied())
+    }
+
+    fn put_node(&mut self, key: NodeKey, node: Option<TreeNode>) -> Result<(), Infallible> {
+        match node {
+            Some(node) => self.insert(key, node),
+            None => self.remove(&key),
+        };
+        Ok(())
+    }
+}
+
+fn hash_of(node: &Option<TreeNode>) -> [u8; 32] {
+    node.as_ref().map(TreeNode::hash).unwrap_or(EMPTY_HASH)
+}
+
+/// Reads the current root out of a node store.
+pub fn root<S: NodeStore>(store: &S) -> Result<[u8; 32], S::Error> {
+    Ok(hash_of(&store.get_node(&NodeKey::ROOT)?))
+}
+
+/// Sets the leaf for `id` (`None` removes it) and rehashes the path up to the root.
+pub fn update<S: NodeStore>(
+    store: &mut S,
+    id: &AccountId,
+    leaf: Option<[u8; 32]>,
+) -> Result<(), S::Error> {
+    update_at(store, 0, id, leaf).map(|_| ())
+}
+
+/// Rewrites the subtree at `depth` on the path to `id` and returns its new top node.
+fn update_at<S: NodeStore>(
+    store: &mut S,
+    depth: usize,
+    id: &AccountId,
+    leaf: Option<[u8; 32]>,
+) -> Result<Option<TreeNode>, S::Error> {
+    let key = NodeKey::on_path(id, depth);
+    let current = store.get_node(&key)?;
+
+    let new = match current {
+        // Empty slot: the account becomes a leaf here.
+        None => leaf.map(|hash| TreeNode::Leaf(*id, hash)),
+
+        // The account itself: replace or remove.
+        Some(TreeNode::Leaf(other, _)) if other == *id => {
+            leaf.map(|hash| TreeNode::Leaf(*id, hash))
+        }
+
+        // Another account occupies the slot: push both down until their paths diverge.
+        Some(TreeNode::Leaf(other, other_hash)) => match leaf {
+            Some(hash) => Some(split(store, depth, (other, other_hash), (*id, hash))?),
+            None => return Ok(current),
+        },
+
+        Some(TreeNode::Internal(_)) => {
+            let child_key = NodeKey::on_path(id, depth + 1);
+            let sibling_key = child_key.sibling();
+
+            let child = update_at(store, depth + 1, id, leaf)?;
+            let sibling = store.get_node(&sibling_key)?;
+
+            match (child, sibling) {
+                // A lone leaf moves up to keep the tree canonical.
+                (Some(node @ TreeNode::Leaf(..)), None)
+                | (None, Some(node @ TreeNode::Leaf(..))) => {
+                    store.put_node(child_key, None)?;
+                    store.put_node(sibling_key, None)?;
+                    Some(node)
+                }
+                (None, None) => None,
+                _ => {
+                    let (left, right) = if path_bit(id, depth) {
+                        (hash_of(&sibling), hash_of(&child))
+                    } else {
+                        (hash_of(&child), hash_of(&sibling))
+                    };
+                    Some(TreeNode::Internal(node_hash(&left, &right)))
+                }
+            }
+        }
+    };
+
+    if new != current {
+        store.put_node(key, new)?;
+    }
+    Ok(new)
+}
+
+/// Builds the subtree at `depth` holding exactly the two leaves `a` and `b`.
+/// Writes everything below `depth`; the caller writes the returned node.
+fn split<S: NodeStore>(
+    store: &mut S,
+    depth: usize,
+    a: (AccountId, [u8; 32]),
+    b: (AccountId, [u8; 32]),
+) -> Result<TreeNode, S::Error> {
+    let a_bit = path_bit(&a.0, depth);
+    let b_bit = path_bit(&b.0, depth);
+
+    let (left, right) = if a_bit == b_bit {
+        // Still sharing a prefix: one internal child, one empty child.
+        let child = split(store, depth + 1, a, b)?;
+        store.put_node(NodeKey::on_path(&a.0, depth + 1), Some(child))?;
+        if a_bit {
+            (EMPTY_HASH, child.hash())
+        } else {
+            (child.hash(), EMPTY_HASH)
+        }
+    } else {
+        store.put_node(
+            NodeKey::on_path(&a.0, depth + 1),
+            Some(TreeNode::Leaf(a.0, a.1)),
+        )?;
+        store.put_node(
+            NodeKey::on_path(&b.0, depth + 1),
+            Some(TreeNode::Leaf(b.0, b.1)),
+        )?;
+        if a_bit {
+            (b.1, a.1)
+        } else {
+            (a.1, b.1)
+        }
+    };
+
+    Ok(TreeNode::Internal(node_hash(&left, &right)))
+}
+
+/// The walk from the root down to where an account's leaf is (or would be).
+pub struct TreePath {
+    /// Sibling hashes along the path, from the root down.
+    pub siblings: Vec<[u8; 32]>,
+    /// The node the path ends on: the account's leaf, another leaf, or empty.
+    pub terminal: Option<TreeNode>,
+}
+
+pub fn path<S: NodeStore>(store: &S, id: &AccountId) -> Result<TreePath, S::Error> {
+    let mut siblings = Vec::new();
+    loop {
+        let depth = siblings.len();
+        match store.get_node(&NodeKey::on_path(id, depth))? {
+            Some(TreeNode::Internal(_)) => {
+                let sibling_key = NodeKey::on_path(id, depth + 1).sibling();
+                siblings.push(hash_of(&store.get_node(&sibling_key)?));
+            }
+            terminal => return Ok(TreePath { siblings, terminal }),
+        }
+    }
+}
+
+/// What a proof claims sits at the end of the path to an account.
+#[derive(Clone, Debug, PartialEq, Eq, SchemaRead, SchemaWrite)]
+pub enum ProofLeaf {
+    /// Inclusion: the account exists with this state.
+    Present(AccountData),
+
+    /// Exclusion: the path ends in an empty subtree.
+    Empty,
+
+    /// Exclusion: the path ends at a different account's leaf (its ID and state).
+    Other(AccountId, AccountData),
+}
+
+/// An inclusion or exclusion proof for a single account.
+#[derive(Clone, Debug, PartialEq, Eq, SchemaRead, SchemaWrite)]
+pub struct MerkleProof {
+    /// Sibling hashes along the path, from the root down.
+    pub siblings: Vec<[u8; 32]>,
+    pub leaf: ProofLeaf,
+}
+
+impl MerkleProof {
+    /// The proven account state, or `None` for an exclusion proof.
+    pub fn account(&self) -> Option<&AccountData> {
+        match &self.leaf {
+            ProofLeaf::Present(data) => Some(data),
+            _ => None,
+        }
+    }
+}
+
+/// Checks `proof` for `id` against `root`.
+/// On success, `proof.leaf` is authenticated: `Present` proves the account's state,
+/// the other variants prove the account does not exist (i.e. has the default state).
+pub fn verify(root: &[u8; 32], id: &AccountId, proof: &MerkleProof) -> bool {
+    let depth = proof.siblings.len();
+    if depth > TREE_DEPTH {
+        return false;
+    }
+
+    let mut hash = match &proof.leaf {
+        ProofLeaf::Present(data) if !data.is_empty() => leaf_hash(id, data),
+        ProofLeaf::Present(_) => return false,
+        ProofLeaf::Empty => EMPTY_HASH,
+        ProofLeaf::Other(other, data) => {
+            // The other leaf must really live on our path, and is hashed here
+            // so a subtree hash can't be passed off as a leaf.
+            if other == id
+                || data.is_empty()
+                || NodeKey::on_path(other, depth) != NodeKey::on_path(id, depth)
+            {
+                return false;
+            }
+            leaf_hash(other, data)
+        }
+    };
+
+    for (depth, sibling) in proof.siblings.iter().enumerate().rev() {
+        hash = if path_bit(id, depth) {
+            node_hash(sibling, &hash)
+        } else {
+            node_hash(&hash, sibling)
+        };
+    }
+
+    hash == *root
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum MerkleError {
+    #[error("Malformed multiproof")]
+    MalformedProof,
+
+    #[error("{0:?} is not covered by the multiproof")]
+    NotCovered(AccountId),
+}
+
+/// One node of a `MultiProof`, in pre-order.
+#[derive(Clone, Debug, PartialEq, Eq, SchemaRead, SchemaWrite)]
+pub enum MultiProofNode {
+    /// An empty subtree.
+    Empty,
+
+    /// A single-account subtree: a covered account or a neighbour next to one.
+    /// Carries the account's state so the verifier hashes the leaf itself.
+    Leaf(AccountId, AccountData),
+
+    /// A subtree off every covered path, known only by its children's hashes.
+    /// Always holds two or more accounts (single accounts are sent as `Leaf`),
+    /// so removing a covered account can still collapse the tree correctly.
+    /// Hashing the children here proves it is an internal node, not a leaf.
+    Pruned([u8; 32], [u8; 32]),
+
+    /// An internal node on a covered path; its left then right subtree follow.
+    Branch,
+}
+
+/// A compact proof for many accounts at once: the tree pruned down to the paths
+/// leading to those accounts. Shared path nodes appear once.
+#[derive(Clone, Debug, Default, PartialEq, Eq, SchemaRead, SchemaWrite)]
+pub struct MultiProof {
+    pub nodes: Vec<MultiProofNode>,
+}
+
+/// Builds the multiproof covering `ids` out of a node store.
+/// `account` looks up the state of every leaf the proof reaches.
+pub fn multiproof<S, F>(store: &S, ids: &[AccountId], account: F) -> Result<MultiProof, S::Error>
+where
+    S: NodeStore,
+    F: Fn(&AccountId) -> Result<AccountData, S::Error>,
+{
+    let mut ids = ids.to_vec();
+    // Byte order is path order, so every subtree's accounts stay contiguous.
+    ids.sort_by_key(|id| id.0);
+    ids.dedup();
+
+    let mut nodes = Vec::new();
+    write_subtree(store, &account, NodeKey::ROOT, &ids, &mut nodes)?;
+    Ok(MultiProof { nodes })
+}
+
+fn write_subtree<S, F>(
+    store: &S,
+    account: &F,
+    key: NodeKey,
+    ids: &[AccountId],
+    out: &mut Vec<MultiProofNode>,
+) -> Result<(), S::Error>
+where
+    S: NodeStore,
+    F: Fn(&AccountId) -> Result<AccountData, S::Error>,
+{
+    match store.get_node(&key)? {
+        None => out.push(MultiProofNode::Empty),
+        Some(TreeNode::Leaf(id, _)) => out.push(MultiProofNode::Leaf(id, account(&id)?)),
+        Some(TreeNode::Internal(_)) if ids.is_empty() => out.push(MultiProofNode::Pruned(
+            hash_of(&store.get_node(&key.child(false))?),
+            hash_of(&store.get_node(&key.child(true))?),
+        )),
+        Some(TreeNode::Internal(_)) => {
+            out.push(MultiProofNode::Branch);
+            let depth = key.depth as usize;
+            let split = ids.partition_point(|id| !path_bit(id, depth));
+            write_subtree(store, account, key.child(false), &ids[..split], out)?;
+            write_subtree(store, account, key.child(true), &ids[split..], out)?;
+        }
+    }
+    Ok(())
+}
+
+/// The tree rebuilt from a `MultiProof`.
+/// Covered accounts can be read and updated, and the root recomputed, without the
+/// rest of the state. Used by the Prover (Guest).
+#[derive(Clone, Debug, Default)]
+pub struct PartialTree {
+    nodes: HashMap<NodeKey, TreeNode>,
+    pruned: HashSet<NodeKey>,
+}
+
+impl PartialTree {
+    /// Rebuilds the pruned tree, rejecting proofs that are not canonically shaped.
+    pub fn from_proof(proof: &MultiProof) -> Result<Self, MerkleError> {
+        let mut tree = Self::default();
+        let mut nodes = proof.nodes.iter();
+
+        tree.read_subtree(&mut nodes, NodeKey::ROOT)?;
+        if nodes.next().is_some() {
+            return Err(MerkleError::MalformedProof);
+        }
+        Ok(tree)
+    }
+
+    fn read_subtree<'a>(
+        &mut self,
+        nodes: &mut impl Iterator<Item = &'a MultiProofNode>,
+        key: NodeKey,
+    ) -> Result<Option<TreeNode>, MerkleError> {
+        let node = match nodes.next().ok_or(MerkleError::MalformedProof)? {
+            MultiProofNode::Empty => return Ok(None),
+            MultiProofNode::Leaf(id, data) => {
+                // A leaf can only live on its own path, and empty accounts are never stored.
+                if NodeKey::on_path(id, key.depth as usize) != key || data.is_empty() {
+                    return Err(MerkleError::MalformedProof);
+                }
+                TreeNode::Leaf(*id, leaf_hash(id, data))
+            }
+            MultiProofNode::Pruned(left, right) => {
+                // Only where a `Branch` could be
+                if key.depth as usize >= TREE_DEPTH {
+                    return Err(MerkleError::MalformedProof);
+                }
+                self.pruned.insert(key);
+                TreeNode::Internal(node_hash(left, right))
+            }
+            MultiProofNode::Branch => {
+                if key.depth as usize >= TREE_DEPTH {
+                    return Err(MerkleError::MalformedProof);
+                }
+                let left = self.read_subtree(nodes, key.child(false))?;
+                let right = self.read_subtree(nodes, key.child(true))?;
+
+                match (&left, &right) {
+                    // These would have collapsed into a leaf or nothing.
+                    (None, None)
+                    | (Some(TreeNode::Leaf(..)), None)
+                    | (None, Some(TreeNode::Leaf(..))) => return Err(MerkleError::MalformedProof),
+                    _ => TreeNode::Internal(node_hash(&hash_of(&left), &hash_of(&right))),
+                }
+            }
+        };
+
+        self.nodes.insert(key, node);
+        Ok(Some(node))
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        root(&self.nodes).unwrap_or_else(|e| match e {})
+    }
+
+    /// The leaf hash of `id` (`None` if the account does not exist).
+    /// Fails if the proof does not reach far enough to tell.
+    pub fn leaf(&self, id: &AccountId) -> Result<Option<[u8; 32]>, MerkleError> {
+        for depth in 0..=TREE_DEPTH {
+            let key = NodeKey::on_path(id, depth);
+            if self.pruned.contains(&key) {
+                return Err(MerkleError::NotCovered(*id));
+            }
+            match self.nodes.get(&key) {
+                None => return Ok(None),
+                Some(TreeNode::Leaf(other, hash)) => {
+                    return Ok((other == id).then_some(*hash));
+                }
+                Some(TreeNode::Internal(_)) => {}
+            }
+        }
+        Err(MerkleError::MalformedProof)
+    }
+
+    /// Sets the leaf for a covered account (`None` removes it).
+    pub fn update(&mut self, id: &AccountId, leaf: Option<[u8; 32]>) -> Result<(), MerkleError> {
+        // Covered paths never run into a pruned subtree, so the generic update is safe.
+        self.leaf(id)?;
+        update(&mut self.nodes, id, leaf).unwrap_or_else(|e| match e {});
+        Ok(())
+    }
+}
+
+/// An in-memory sparse Merkle tree over the account state.
+///
+/// The root depends only on the set of accounts, never on insertion order,
+/// so the Sequencer (Host) and the Prover (Guest) always agree on it.
+#[derive(Clone, Default, Debug)]
+pub struct SparseMerkleTree {
+    accounts: HashMap<AccountId, AccountData>,
+    nodes: HashMap<NodeKey, TreeNode>,
+}
+
+impl SparseMerkleTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, id: &AccountId) -> Option<&AccountData> {
+        self.accounts.get(id)
+    }
+
+    /// Inserts or replaces an account.
+    /// Setting the default (empty) state removes the account from the tree.
+    pub fn insert(&mut self, id: AccountId, data: AccountData) {
+        let leaf = if data.is_empty() {
+            self.accounts.remove(&id);
+            None
+        } else {
+            let hash = leaf_hash(&id, &data);
+            self.accounts.insert(id, data);
+            Some(hash)
+        };
+
+        update(&mut self.nodes, &id, leaf).unwrap_or_else(|e| match e {});
+    }
+
+    /// Number of non-empty accounts.
+    pub fn len(&self) -> usize {
+        self.accounts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&AccountId, &AccountData)> {
+        self.accounts.iter()
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        root(&self.nodes).unwrap_or_else(|e| match e {})
+    }
+
+    /// Produces an inclusion proof if the account exists, an exclusion proof otherwise.
+    pub fn prove(&self, id: &AccountId) -> MerkleProof {
+        let TreePath { siblings, terminal } = path(&self.nodes, id).unwrap_or_else(|e| match e {});
+
+        let leaf = match terminal {
+            Some(TreeNode::Leaf(other, _)) if other == *id => {
+                ProofLeaf::Present(self.accounts[id].clone())
+            }
+            Some(TreeNode::Leaf(other, _)) => {
+                ProofLeaf::Other(other, self.accounts[&other].clone())
+            }
+            _ => ProofLeaf::Empty,
+        };
+
+        MerkleProof { siblings, leaf }
+    }
+
+    pub fn verify(root: &[u8; 32], id: &AccountId, proof: &MerkleProof) -> bool {
+        verify(root, id, proof)
+    }
+
+    /// A single proof for all of `ids` (existing or not) against `root()`.
+    pub fn multiproof(&self, ids: &[AccountId]) -> MultiProof {
+        multiproof(&self.nodes, ids, |id| Ok(self.accounts[id].clone()))
+            .unwrap_or_else(|e| match e {})
+    }
+}
+
+/// Root of an ordered list of hashes (e.g. the transactions of a batch).
+/// A plain binary tree over `leaves` padded with `EMPTY_HASH` to a power of two.
+/// The empty list hashes to `EMPTY_HASH`.
+pub fn list_root(leaves: &[[u8; 32]]) -> [u8; 32] {
+    if leaves.is_empty() {
+        return EMPTY_HASH;
+    }
+
+    let mut level = leaves.to_vec();
+    level.resize(leaves.len().next_power_of_two(), EMPTY_HASH);
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| node_hash(&pair[0], &pair[1]))
+            .collect();
+    }
+    level[0]
+}
+
+/// Inclusion proof for one entry of a `list_root` tree.
+#[derive(Clone, Debug, PartialEq, Eq, SchemaRead, SchemaWrite)]
+pub struct ListProof {
+    /// Position of the leaf in the list.
+    pub index: u64,
+    /// Sibling hashes from the leaf level up to just below the root.
+    /// At level `i` the sibling is on the left when bit `i` of `index` is set.
+    pub siblings: Vec<[u8; 32]>,
+}
+
+/// Proves `leaves[index]` against `list_root(leaves)`. `None` if `index` is out of range.
+pub fn list_proof(leaves: &[[u8; 32]], index: usize) -> Option<ListProof> {
+    if index >= leaves.len() {
+        return None;
+    }
+
+    let mut siblings = Vec::new();
+    let mut level = leaves.to_vec();
+    level.resize(leaves.len().next_power_of_two(), EMPTY_HASH);
+    let mut position = index;
+    while level.len() > 1 {
+        siblings.push(level[position ^ 1]);
+        level = level
+            .chunks(2)
+            .map(|pair| node_hash(&pair[0], &pair[1]))
+            .collect();
+        position /= 2;
+    }
+
+    Some(ListProof {
+        index: index as u64,
+        siblings,
+    })
+}
+
+/// Checks that `leaf` sits at `proof.index` of the list with root `root`.
+pub fn verify_list_proof(root: &[u8; 32], leaf: &[u8; 32], proof: &ListProof) -> bool {
+    if proof.siblings.len() >= 64 || proof.index >> proof.siblings.len() != 0 {
+        return false;
+    }
+
+    let mut hash = *leaf;
+    for (level, sibling) in proof.siblings.iter().enumerate() {
+        hash = if (proof.index >> level) & 1 == 1 {
+            node_hash(sibling, &hash)
+        } else {
+            node_hash(&hash, sibling)
+        };
+    }
+    hash == *root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(seed: u8) -> AccountId {
+        AccountId(crate::crypto::hash_bytes(&[seed]))
+    }
+
+    fn data(balance: u64, nonce: u64) -> AccountData {
+        AccountData { balance, nonce }
+    }
+
+    #[test]
+    fn test_root_is_order_independent() {
+        let mut a = SparseMerkleTree::new();
+        let mut b = SparseMerkleTree::new();
+
+        for i in 0..50 {
+            a.insert(id(i), data(i as u64 + 1, 0));
+        }
+        for i in (0..50).rev() {
+            b.insert(id(i), data(i as u64 + 1, 0));
+        }
+
+        assert_eq!(a.root(), b.root());
+        assert_ne!(a.root(), EMPTY_HASH);
+    }
+
+    #[test]
+    fn test_removal_restores_previous_root() {
+        let mut tree = SparseMerkleTree::new();
+        for i in 0..20 {
+            tree.insert(id(i), data(100, 1));
+        }
+        let before = tree.root();
+
+        tree.insert(id(200), data(5, 0));
+        assert_ne!(tree.root(), before);
+
+        tree.insert(id(200), AccountData::default());
+        assert_eq!(tree.root(), before);
+
+        for i in 0..20 {
+            tree.insert(id(i), AccountData::default());
+        }
+        assert_eq!(tree.root(), EMPTY_HASH);
+    }
+
+    #[test]
+    fn test_inclusion_and_exclusion_proofs() {
+        let mut tree = SparseMerkleTree::new();
+        for i in 0..30 {
+            tree.insert(id(i), data(i as u64 * 10 + 1, i as u64));
+        }
+        let root = tree.root();
+
+        for i in 0..30 {
+            let proof = tree.prove(&id(i));
+            assert_eq!(proof.account(), Some(&data(i as u64 * 10 + 1, i as u64)));
+            assert!(SparseMerkleTree::verify(&root, &id(i), &proof));
+        }
+
+        for i in 30..60 {
+            let proof = tree.prove(&id(i));
+            assert!(proof.account().is_none());
+            assert!(SparseMerkleTree::verify(&root, &id(i), &proof));
+        }
+    }
+
+    #[test]
+    fn test_tampered_proof_is_rejected() {
+        let mut tree = SparseMerkleTree::new();
+        for i in 0..10 {
+            tree.insert(id(i), data(100, 0));
+        }
+        let root = tree.root();
+
+        // Lie about the balance.
+        let mut proof = tree.prove(&id(3));
+        proof.leaf = ProofLeaf::Present(data(1_000_000, 0));
+        assert!(!verify(&root, &id(3), &proof));
+
+        // Claim an existing account is absent.
+        let mut proof = tree.prove(&id(3));
+        proof.leaf = ProofLeaf::Empty;
+        assert!(!verify(&root, &id(3), &proof));
+
+        // Reuse another account's proof.
+        let proof = tree.prove(&id(4));
+        assert!(!verify(&root, &id(3), &proof));
+
+        // Pass the whole tree off as a neighbour leaf to "exclude" an existing account.
+        let forged = MerkleProof {
+            siblings: Vec::new(),
+            leaf: ProofLeaf::Other(id(4), data(100, 0)),
+        };
+        assert!(!verify(&root, &id(3), &forged));
+        let mut single = SparseMerkleTree::new();
+        single.insert(id(4), data(100, 0));
+        assert!(verify(&single.root(), &id(3), &forged));
+    }
+
+    #[test]
+    fn test_multiproof_updates_match_full_tree() {
+        let mut tree = SparseMerkleTree::new();
+        for i in 0..100 {
+            tree.insert(id(i), data(1_000, 0));
+        }
+        let pre_root = tree.root();
+
+        // Touch some existing accounts and some fresh ones.
+        let touched: Vec<AccountId> = [3, 17, 42, 99, 150, 151].into_iter().map(id).collect();
+        let proof = tree.multiproof(&touched);
+
+        let mut partial = PartialTree::from_proof(&proof).unwrap();
+        assert_eq!(partial.root(), pre_root);
+        assert_eq!(
+            partial.leaf(&id(3)).unwrap(),
+            Some(leaf_hash(&id(3), &data(1_000, 0)))
+        );
+        assert_eq!(partial.leaf(&id(150)).unwrap(), None);
+
+        // Untouched accounts are out of reach.
+        assert!(partial.leaf(&id(50)).is_err());
+
+        for (i, account) in touched.iter().enumerate() {
+            let new = if i == 2 {
+                AccountData::default()
+            } else {
+                data(500 + i as u64, 1)
+            };
+            partial
+                .update(account, account_leaf(account, &new))
+                .unwrap();
+            tree.insert(*account, new);
+        }
+
+        assert_eq!(partial.root(), tree.root());
+        assert!(partial.update(&id(50), None).is_err());
+    }
+
+    #[test]
+    fn test_malformed_multiproof_is_rejected() {
+        let mut tree = SparseMerkleTree::new();
+        for i in 0..10 {
+            tree.insert(id(i), data(1, 0));
+        }
+        let mut proof = tree.multiproof(&[id(1)]);
+
+        proof.nodes.push(MultiProofNode::Empty);
+        assert_eq!(
+            PartialTree::from_proof(&proof).unwrap_err(),
+            MerkleError::MalformedProof
+        );
+
+        proof.nodes.truncate(1);
+        assert!(PartialTree::from_proof(&proof).is_err());
+    }
+
+    #[test]
+    fn test_adjacent_keys() {
+        // Keys differing only in the last bit force leaves down to full depth.
+        let mut a = AccountId([0xAB; 32]);
+        let mut b = a;
+        a.0[31] = 0xA0;
+        b.0[31] = 0xA1;
+
+        let mut tree = SparseMerkleTree::new();
+        tree.insert(a, data(1, 0));
+        tree.insert(b, data(2, 0));
+
+        let root = tree.root();
+        let proof = tree.prove(&b);
+        assert_eq!(proof.siblings.len(), TREE_DEPTH);
+        assert!(verify(&root, &b, &proof));
+
+        tree.insert(a, AccountData::default());
+        assert_eq!(tree.root(), leaf_hash(&b, &data(2, 0)));
+    }
+
+    #[test]
+    fn test_list_proofs() {
+        for len in 1..=9u8 {
+            let leaves: Vec<[u8; 32]> = (0..len).map(|i| id(i).0).collect();
+            let root = list_root(&leaves);
+            for (i, leaf) in leaves.iter().enumerate() {
+                let proof = list_proof(&leaves, i).unwrap();
+                assert!(verify_list_proof(&root, leaf, &proof));
+                // The same leaf at another position doesn't verify
+                let moved = ListProof {
+                    index: proof.index ^ 1,
+                    ..proof.clone()
+                };
+                assert!(!verify_list_proof(&root, leaf, &moved));
+            }
+            assert_eq!(list_proof(&leaves, len as usize), None);
+        }
+    }
+}
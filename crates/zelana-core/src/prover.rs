@@ -1,26 +1,216 @@
 use {
-    crate::{AccountId, L2Transaction},
-    std::collections::HashMap,
+    crate::{
+        batch::transactions_root, crypto::to_bytes, merkle::MultiProof,
+        withdrawal::withdrawals_root, AccountId, L2Transaction, WithdrawData,
+    },
+    thiserror::Error,
     wincode::{SchemaRead, SchemaWrite},
 };
 
 /// The input fed into the SP1 ZKVM.
 #[derive(SchemaRead, SchemaWrite, Debug, Clone)]
 pub struct BatchInput {
+    /// Height of the batch on L2.
+    pub batch_height: u64,
+
+    /// The chain every transfer and withdrawal in the batch must be signed for.
+    pub chain_id: u64,
+
+    /// `l1_seq` of the last deposit applied before this batch (`None` if there was none).
+    /// The batch's deposits must continue from it without gaps.
+    pub prev_l1_seq: Option<u64>,
+
     /// The Merkle Root of the state BEFORE this batch.
     pub pre_state_root: [u8; 32],
 
     /// The ordered list of transactions to execute.
     pub transactions: Vec<L2Transaction>,
 
-    /// The subset of the State Database needed to execute these transactions.
-    /// future->Merkle Proofs. For MVP, it's the raw accounts).
-    pub witness_accounts: HashMap<AccountId, AccountData>,
+    /// Pre-state of every account the batch reads or writes (including ones that don't exist yet).
+    pub witness_accounts: Vec<AccountWitness>,
+
+    /// Multiproof authenticating `witness_accounts` against `pre_state_root`.
+    /// The Guest recomputes the post-state root from the same paths.
+    pub witness_proof: MultiProof,
+}
+
+/// A single account in the batch witness.
+#[derive(SchemaRead, SchemaWrite, Debug, Clone, PartialEq, Eq)]
+pub struct AccountWitness {
+    pub id: AccountId,
+    pub data: AccountData,
 }
 
 /// Helper struct for passing account state across the boundary.
-#[derive(SchemaRead, SchemaWrite, Debug, Clone)]
+#[derive(SchemaRead, SchemaWrite, Debug, Clone, Default, PartialEq, Eq)]
 pub struct AccountData {
     pub balance: u64,
     pub nonce: u64,
 }
+
+impl AccountData {
+    /// An account in the default state is indistinguishable from one that never existed.
+    pub fn is_empty(&self) -> bool {
+        self.balance == 0 && self.nonce == 0
+    }
+}
+
+/// What the Guest commits for a batch: the statement an L1 verifier checks a proof against.
+#[derive(SchemaRead, SchemaWrite, Debug, Clone, PartialEq, Eq)]
+pub struct BatchPublicValues {
+    /// Layout version (`BatchPublicValues::VERSION`). Always encoded first.
+    pub version: u16,
+    pub batch_height: u64,
+    pub chain_id: u64,
+    /// `l1_seq` of the last deposit applied before the batch; `None` if there was none.
+    pub prev_l1_seq: Option<u64>,
+    pub pre_state_root: [u8; 32],
+    pub post_state_root: [u8; 32],
+    /// `batch::transactions_root` of the batch, equal to its header's `transactions_root`.
+    pub transactions_root: [u8; 32],
+    /// The L1 deposits the batch applied; `None` if it has none. The range always
+    /// starts right after `prev_l1_seq`.
+    pub deposits: Option<DepositRange>,
+    /// `withdrawal::withdrawals_root` of the executed withdrawals, what the L1 bridge
+    /// pays out against.
+    pub withdrawals_root: [u8; 32],
+}
+
+/// `l1_seq` of the first and last deposit in a batch, in execution order.
+#[derive(SchemaRead, SchemaWrite, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DepositRange {
+    pub first_l1_seq: u64,
+    pub last_l1_seq: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum PublicValuesError {
+    #[error("Malformed public values")]
+    Malformed,
+    #[error("Unsupported public values version {0}")]
+    UnsupportedVersion(u16),
+}
+
+impl BatchPublicValues {
+    pub const VERSION: u16 = 2;
+
+    /// The public values of executing `input` to `post_state_root`.
+    /// `withdrawals` are the withdrawals that executed successfully, in order.
+    pub fn new(
+        input: &BatchInput,
+        post_state_root: [u8; 32],
+        withdrawals: &[WithdrawData],
+    ) -> Self {
+        let mut seqs = input.transactions.iter().filter_map(|tx| match tx {
+            L2Transaction::Deposit(deposit) => Some(deposit.l1_seq),
+            _ => None,
+        });
+        let deposits = seqs.next().map(|first| DepositRange {
+            first_l1_seq: first,
+            last_l1_seq: seqs.last().unwrap_or(first),
+        });
+
+        Self {
+            version: Self::VERSION,
+            batch_height: input.batch_height,
+            chain_id: input.chain_id,
+            prev_l1_seq: input.prev_l1_seq,
+            pre_state_root: input.pre_state_root,
+            post_state_root,
+            transactions_root: transactions_root(&input.transactions),
+            deposits,
+            withdrawals_root: withdrawals_root(withdrawals),
+        }
+    }
+
+    /// `l1_seq` of the last deposit applied once the batch is executed.
+    pub fn last_l1_seq(&self) -> Option<u64> {
+        self.deposits
+            .map(|range| range.last_l1_seq)
+            .or(self.prev_l1_seq)
+    }
+
+    /// The bytes the Guest commits.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        to_bytes(self)
+    }
+
+    /// Decodes committed public values, rejecting versions this build doesn't know.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PublicValuesError> {
+        check_version(bytes, Self::VERSION)?;
+        wincode::deserialize(bytes).map_err(|_| PublicValuesError::Malformed)
+    }
+}
+
+/// Checks the layout version public values are encoded with (their first u16, little-endian).
+pub(crate) fn check_version(bytes: &[u8], expected: u16) -> Result<(), PublicValuesError> {
+    let Some(&[lo, hi]) = bytes.get(..2) else {
+        return Err(PublicValuesError::Malformed);
+    };
+    let version = u16::from_le_bytes([lo, hi]);
+    if version != expected {
+        return Err(PublicValuesError::UnsupportedVersion(version));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::DepositEvent};
+
+    fn deposit(l1_seq: u64) -> L2Transaction {
+        L2Transaction::Deposit(DepositEvent {
+            to: AccountId([1u8; 32]),
+            amount: 10,
+            l1_seq,
+        })
+    }
+
+    fn input(transactions: Vec<L2Transaction>) -> BatchInput {
+        BatchInput {
+            batch_height: 3,
+            chain_id: 1,
+            prev_l1_seq: Some(6),
+            pre_state_root: [1u8; 32],
+            transactions,
+            witness_accounts: vec![],
+            witness_proof: MultiProof { nodes: vec![] },
+        }
+    }
+
+    #[test]
+    fn test_public_values_round_trip() {
+        let values = BatchPublicValues::new(
+            &input(vec![deposit(7), deposit(8), deposit(9)]),
+            [2u8; 32],
+            &[],
+        );
+        assert_eq!(
+            values.deposits,
+            Some(DepositRange {
+                first_l1_seq: 7,
+                last_l1_seq: 9
+            })
+        );
+        assert_eq!(values.last_l1_seq(), Some(9));
+        assert_eq!(
+            BatchPublicValues::from_bytes(&values.to_bytes()),
+            Ok(values.clone())
+        );
+
+        let empty = BatchPublicValues::new(&input(vec![]), [2u8; 32], &[]);
+        assert_eq!(empty.deposits, None);
+        assert_eq!(empty.last_l1_seq(), Some(6));
+
+        let mut future = values.to_bytes();
+        future[0] = 3;
+        assert_eq!(
+            BatchPublicValues::from_bytes(&future),
+            Err(PublicValuesError::UnsupportedVersion(3))
+        );
+        assert_eq!(
+            BatchPublicValues::from_bytes(&values.to_bytes()[..10]),
+            Err(PublicValuesError::Malformed)
+        );
+    }
+}
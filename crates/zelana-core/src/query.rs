@@ -0,0 +1,29 @@
+use {
+    crate::{identity::AccountId, transaction::DepositEvent},
+    wincode::{SchemaRead, SchemaWrite},
+};
+
+/// A read-only request a client sends the sequencer in a `Query` packet.
+#[derive(Debug, Clone, PartialEq, Eq, SchemaRead, SchemaWrite)]
+pub enum Query {
+    /// Deposits to `account` that the sequencer has seen on L1 but not credited yet.
+    PendingDeposits { account: AccountId },
+}
+
+/// The sequencer's answer to a `Query`, sent back in a `Reply` packet.
+#[derive(Debug, Clone, PartialEq, Eq, SchemaRead, SchemaWrite)]
+pub enum QueryReply {
+    /// Oldest first. Capped so the reply fits in one datagram, so it may not list them all.
+    PendingDeposits(Vec<PendingDeposit>),
+}
+
+/// A deposit seen on L1 but not credited yet: its L1 transaction hasn't reached the
+/// sequencer's finality rule. It can still be dropped if L1 reorgs.
+#[derive(Debug, Clone, PartialEq, Eq, SchemaRead, SchemaWrite)]
+pub struct PendingDeposit {
+    pub event: DepositEvent,
+    /// Base58 signature of the L1 transaction that made it.
+    pub signature: String,
+    /// Slot the transaction was last seen in.
+    pub slot: u64,
+}
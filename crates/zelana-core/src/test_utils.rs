@@ -0,0 +1,74 @@
+//! Helpers shared by the workspace's tests. Behind the `test-utils` feature.
+
+use {
+    crate::{
+        identity::AccountId,
+        transaction::{
+            L2Transaction, SignedTransaction, TransactionData, WithdrawData, WithdrawRequest,
+        },
+    },
+    ed25519_dalek::{Signer, SigningKey},
+    std::path::PathBuf,
+};
+
+/// A transfer of `amount` from `key`'s L1-mapped account, signed on chain 1.
+pub fn signed_transfer(key: &SigningKey, to: AccountId, amount: u64, nonce: u64) -> L2Transaction {
+    let data = TransactionData {
+        from: AccountId(key.verifying_key().to_bytes()),
+        to,
+        amount,
+        nonce,
+        chain_id: 1,
+    };
+    L2Transaction::Transfer(SignedTransaction {
+        signature: key.sign(&data.signing_message()).to_bytes().to_vec(),
+        data,
+        signer_pubkey: key.verifying_key().to_bytes(),
+        privacy_pubkey: None,
+    })
+}
+
+/// A withdrawal of `amount` from `key`'s L1-mapped account, signed on chain 1.
+pub fn signed_withdrawal(
+    key: &SigningKey,
+    to_l1_address: [u8; 32],
+    amount: u64,
+    nonce: u64,
+) -> L2Transaction {
+    let data = WithdrawData {
+        from: AccountId(key.verifying_key().to_bytes()),
+        to_l1_address,
+        amount,
+        nonce,
+        chain_id: 1,
+    };
+    L2Transaction::Withdraw(WithdrawRequest {
+        signature: key.sign(&data.signing_message()).to_bytes().to_vec(),
+        data,
+        signer_pubkey: key.verifying_key().to_bytes(),
+        privacy_pubkey: None,
+    })
+}
+
+/// A transfer with no signature, for code that runs after signatures are checked.
+pub fn unsigned_transfer(from: AccountId, to: AccountId, amount: u64, nonce: u64) -> L2Transaction {
+    L2Transaction::Transfer(SignedTransaction {
+        data: TransactionData {
+            from,
+            to,
+            amount,
+            nonce,
+            chain_id: 1,
+        },
+        signature: vec![],
+        signer_pubkey: [0u8; 32],
+        privacy_pubkey: None,
+    })
+}
+
+/// A fresh path under the temp dir, unique to `name` and this process.
+pub fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("zelana-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&path);
+    path
+}
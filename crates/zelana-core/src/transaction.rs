@@ -1,5 +1,8 @@
 use {
-    crate::identity::AccountId,
+    crate::{
+        crypto::{hash_bytes, to_bytes},
+        identity::AccountId,
+    },
     wincode::{SchemaRead, SchemaWrite},
 };
 
@@ -16,6 +19,13 @@ pub enum L2Transaction {
     Withdraw(WithdrawRequest),
 }
 
+impl L2Transaction {
+    /// The transaction hash: BLAKE3 of its serialized form.
+    pub fn hash(&self) -> [u8; 32] {
+        hash_bytes(&to_bytes(self))
+    }
+}
+
 /// The payload a user signs.
 #[derive(Debug, Clone, PartialEq, SchemaRead, SchemaWrite)]
 pub struct TransactionData {
@@ -35,22 +45,37 @@ pub struct SignedTransaction {
     pub signature: Vec<u8>,
     /// The raw public key of the signer.
     pub signer_pubkey: [u8; 32],
+    /// The privacy key `data.from` was derived with (`IdentityKeys::derive_id`).
+    /// `None` when `data.from` is the signer key itself (L1-mapped accounts).
+    pub privacy_pubkey: Option<[u8; 32]>,
 }
 
 /// Event coming from the L1 Listener.
-#[derive(Debug, Clone, SchemaRead, SchemaWrite)]
+#[derive(Debug, Clone, PartialEq, Eq, SchemaRead, SchemaWrite)]
 pub struct DepositEvent {
     pub to: AccountId,
     pub amount: u64,
     pub l1_seq: u64,
 }
 
-#[derive(Debug, Clone, SchemaRead, SchemaWrite)]
-pub struct WithdrawRequest {
+/// The payload a user signs to withdraw funds back to L1.
+#[derive(Debug, Clone, PartialEq, SchemaRead, SchemaWrite)]
+pub struct WithdrawData {
     pub from: AccountId,
+    /// The Solana address that receives the funds.
     pub to_l1_address: [u8; 32],
     pub amount: u64,
     pub nonce: u64,
+    pub chain_id: u64,
+}
+
+/// The authenticated wrapper around WithdrawData.
+#[derive(Debug, Clone, SchemaRead, SchemaWrite)]
+pub struct WithdrawRequest {
+    pub data: WithdrawData,
+    /// The Ed25519 signature of the serialized `data`.
     pub signature: Vec<u8>,
     pub signer_pubkey: [u8; 32],
+    /// Same meaning as `SignedTransaction::privacy_pubkey`.
+    pub privacy_pubkey: Option<[u8; 32]>,
 }
@@ -0,0 +1,94 @@
+use {
+    crate::{
+        merkle::{list_proof, list_root, verify_list_proof, ListProof},
+        transaction::WithdrawData,
+    },
+    blake3::Hasher,
+    wincode::{SchemaRead, SchemaWrite},
+};
+
+// Domain separation from tree nodes and every other leaf type.
+const WITHDRAWAL_PREFIX: &[u8] = b"zelana:withdrawal:v1";
+
+impl WithdrawData {
+    /// The leaf this withdrawal gets in its batch's `withdrawals_root`.
+    /// Logic: Hash( Prefix || From || ToL1Address || Amount || Nonce || ChainId ),
+    /// integers little-endian.
+    /// `from` and `nonce` make every leaf unique, so the bridge can use it to mark
+    /// a withdrawal as claimed.
+    pub fn leaf_hash(&self) -> [u8; 32] {
+        let mut hasher = Hasher::new();
+        hasher.update(WITHDRAWAL_PREFIX);
+        hasher.update(&self.from.0);
+        hasher.update(&self.to_l1_address);
+        hasher.update(&self.amount.to_le_bytes());
+        hasher.update(&self.nonce.to_le_bytes());
+        hasher.update(&self.chain_id.to_le_bytes());
+        hasher.finalize().into()
+    }
+}
+
+/// Root of the batch's withdrawals tree: `list_root` over `WithdrawData::leaf_hash`
+/// of its executed withdrawals, in execution order. The L1 bridge checks these leaves
+/// without needing the full signed requests.
+pub fn withdrawals_root(withdrawals: &[WithdrawData]) -> [u8; 32] {
+    let leaves: Vec<[u8; 32]> = withdrawals.iter().map(WithdrawData::leaf_hash).collect();
+    list_root(&leaves)
+}
+
+/// What a user submits to the L1 bridge to claim a withdrawal from a proven batch.
+#[derive(Debug, Clone, PartialEq, SchemaRead, SchemaWrite)]
+pub struct ExitProof {
+    /// The batch whose proven `withdrawals_root` includes the withdrawal.
+    pub batch_height: u64,
+    pub withdrawal: WithdrawData,
+    /// Inclusion of `withdrawal.leaf_hash()` in the batch's withdrawals tree.
+    pub proof: ListProof,
+}
+
+impl ExitProof {
+    /// Proves `withdrawals[index]`, given all withdrawals of the batch in order.
+    pub fn new(batch_height: u64, withdrawals: &[WithdrawData], index: usize) -> Option<Self> {
+        let leaves: Vec<[u8; 32]> = withdrawals.iter().map(WithdrawData::leaf_hash).collect();
+        Some(Self {
+            batch_height,
+            withdrawal: withdrawals.get(index)?.clone(),
+            proof: list_proof(&leaves, index)?,
+        })
+    }
+
+    /// Checks the proof against the batch's proven withdrawals root.
+    pub fn verify(&self, withdrawals_root: &[u8; 32]) -> bool {
+        verify_list_proof(withdrawals_root, &self.withdrawal.leaf_hash(), &self.proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::AccountId};
+
+    fn withdrawal(nonce: u64) -> WithdrawData {
+        WithdrawData {
+            from: AccountId([1u8; 32]),
+            to_l1_address: [2u8; 32],
+            amount: 100,
+            nonce,
+            chain_id: 1,
+        }
+    }
+
+    #[test]
+    fn test_exit_proof_against_batch_root() {
+        let withdrawals: Vec<WithdrawData> = (0..3).map(withdrawal).collect();
+        let root = withdrawals_root(&withdrawals);
+
+        let exit = ExitProof::new(1, &withdrawals, 2).unwrap();
+        assert!(exit.verify(&root));
+        assert!(ExitProof::new(1, &withdrawals, 3).is_none());
+
+        // The bridge pays out exactly what was burned on L2
+        let mut inflated = exit.clone();
+        inflated.withdrawal.amount += 1;
+        assert!(!inflated.verify(&root));
+    }
+}
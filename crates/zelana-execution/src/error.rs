@@ -0,0 +1,84 @@
+use {
+    thiserror::Error,
+    wincode::{SchemaRead, SchemaWrite},
+    zelana_core::auth::AuthError,
+};
+
+/// Why a transaction could not be executed.
+///
+/// Each variant has a stable numeric `code()` used in receipts and responses to
+/// clients. Codes are never reused or renumbered, and new variants go at the end
+/// so serialized receipts keep decoding.
+#[derive(Debug, Clone, PartialEq, Eq, Error, SchemaRead, SchemaWrite)]
+pub enum ExecutionError {
+    #[error("Nonce mismatch: expected {expected}, got {got}")]
+    NonceMismatch { expected: u64, got: u64 },
+    #[error("Insufficient funds: balance {balance}, needed {needed}")]
+    InsufficientFunds { balance: u64, needed: u64 },
+    #[error("Balance overflow")]
+    Overflow,
+    #[error("Invalid signature")]
+    BadSignature,
+    #[error("Wrong chain: expected {expected}, got {got}")]
+    WrongChain { expected: u64, got: u64 },
+    #[error("Malformed transaction")]
+    MalformedTransaction,
+    #[error("Transaction type not accepted here")]
+    Unsupported,
+    /// The state store failed; says nothing about the transaction itself.
+    #[error("Storage error: {0}")]
+    Storage(String),
+    #[error("Signer does not own the sender account")]
+    NotOwner,
+    /// Deposits apply in `l1_seq` order, without gaps: `got` is a duplicate if it is
+    /// below `expected`, and follows missing deposits if it is above.
+    #[error("Deposit out of order: expected l1_seq {expected}, got {got}")]
+    DepositOutOfOrder { expected: u64, got: u64 },
+}
+
+impl ExecutionError {
+    /// Whether a transaction failing with this error still goes into its batch.
+    /// It was well-formed, authorized and in sequence, but its effects couldn't be
+    /// applied: it gets a `TxStatus::Failed` receipt and uses up the sender's nonce,
+    /// or the deposit's `l1_seq`. Every other error keeps the transaction out of the
+    /// batch.
+    pub fn is_includable(&self) -> bool {
+        matches!(
+            self,
+            ExecutionError::InsufficientFunds { .. } | ExecutionError::Overflow
+        )
+    }
+
+    pub fn code(&self) -> u16 {
+        match self {
+            ExecutionError::NonceMismatch { .. } => 1,
+            ExecutionError::InsufficientFunds { .. } => 2,
+            ExecutionError::Overflow => 3,
+            ExecutionError::BadSignature => 4,
+            ExecutionError::WrongChain { .. } => 5,
+            ExecutionError::MalformedTransaction => 6,
+            ExecutionError::Unsupported => 7,
+            ExecutionError::Storage(_) => 100,
+            ExecutionError::NotOwner => 8,
+            ExecutionError::DepositOutOfOrder { .. } => 9,
+        }
+    }
+}
+
+impl From<AuthError> for ExecutionError {
+    fn from(e: AuthError) -> Self {
+        match e {
+            AuthError::BadSignature => ExecutionError::BadSignature,
+            AuthError::NotOwner(_) => ExecutionError::NotOwner,
+        }
+    }
+}
+
+impl From<anyhow::Error> for ExecutionError {
+    fn from(e: anyhow::Error) -> Self {
+        match e.downcast::<ExecutionError>() {
+            Ok(e) => e,
+            Err(e) => ExecutionError::Storage(format!("{:#}", e)),
+        }
+    }
+}
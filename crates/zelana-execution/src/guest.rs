@@ -0,0 +1,92 @@
+use {
+    crate::{
+        error::ExecutionError,
+        processor::BatchExecutor,
+        receipt::{executed_withdrawals, TxReceipt},
+        witness::WitnessStore,
+    },
+    thiserror::Error,
+    zelana_core::{auth::AuthError, BatchInput, BatchPublicValues, L2Transaction},
+};
+
+/// Why a batch can't be proven. Each of these means the Sequencer built an invalid batch.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum BatchError {
+    #[error("Witness data does not match Pre-State Root: {0}")]
+    BadWitness(String),
+    #[error("Transaction {index} is not authorized: {source}")]
+    Unauthorized { index: usize, source: AuthError },
+    #[error("Transaction {index} is not for chain {chain_id}")]
+    WrongChain { index: usize, chain_id: u64 },
+    #[error("Transaction {index} was rejected (code {}): {source}", .source.code())]
+    Rejected {
+        index: usize,
+        source: ExecutionError,
+    },
+}
+
+/// What proving a batch produces.
+#[derive(Debug, Clone)]
+pub struct BatchOutput {
+    /// Exactly what the Guest commits.
+    pub public_values: BatchPublicValues,
+    /// One receipt per transaction, in batch order.
+    pub receipts: Vec<TxReceipt>,
+}
+
+/// The Guest program: checks `input` and re-executes it on its witness alone.
+///
+/// The SP1 Guest is a thin wrapper around this function, so the host can run (and test)
+/// the exact logic that gets proven.
+pub fn execute_batch(mut input: BatchInput) -> Result<BatchOutput, BatchError> {
+    //Rebuild the touched accounts from the witness and verify them against the Pre-State Root
+    //"Does the witness data match the Root we claimed we started with?"
+    let mut store = WitnessStore::new(
+        input.pre_state_root,
+        std::mem::take(&mut input.witness_accounts),
+        &input.witness_proof,
+    )
+    .map_err(|e| BatchError::BadWitness(e.to_string()))?;
+
+    //Execution Loop
+    //We run the EXACT SAME logic as the Sequencer
+    let mut executor = BatchExecutor::new(&mut store).after_deposit(input.prev_l1_seq);
+    let mut receipts = Vec::with_capacity(input.transactions.len());
+
+    for (index, tx) in input.transactions.iter().enumerate() {
+        //Every transfer and withdrawal must be signed by the owner of the debited account.
+        tx.verify_authorization()
+            .map_err(|source| BatchError::Unauthorized { index, source })?;
+
+        //A signature for another chain must not be replayed here.
+        let chain_id = match tx {
+            L2Transaction::Transfer(signed_tx) => Some(signed_tx.data.chain_id),
+            L2Transaction::Withdraw(req) => Some(req.data.chain_id),
+            L2Transaction::Deposit(_) => None,
+        };
+        if chain_id.is_some_and(|id| id != input.chain_id) {
+            return Err(BatchError::WrongChain {
+                index,
+                chain_id: input.chain_id,
+            });
+        }
+
+        //Balance failures (e.g. insufficient funds) give a Failed receipt and stay provable.
+        //Anything else (e.g. a wrong nonce, or a deposit that skips or repeats an l1_seq)
+        //means the Sequencer put an invalid tx in the batch.
+        let receipt = executor
+            .execute(tx)
+            .map_err(|source| BatchError::Rejected { index, source })?;
+        receipts.push(receipt);
+    }
+
+    //Compute Post-State Root (from the same proof paths, updated in place)
+    let new_root = store.compute_root();
+
+    //Only withdrawals that executed burned funds, so only they can be released on L1.
+    let withdrawals = executed_withdrawals(&input.transactions, &receipts);
+    Ok(BatchOutput {
+        public_values: BatchPublicValues::new(&input, new_root, &withdrawals),
+        receipts,
+    })
+}
@@ -1,19 +1,40 @@
+pub mod error;
+pub mod guest;
 pub mod memory;
+pub mod overlay;
 pub mod processor;
+pub mod receipt;
+pub mod recorder;
 pub mod storage;
+pub mod witness;
 
 pub use {
+    error::ExecutionError,
+    guest::{execute_batch, BatchError, BatchOutput},
     memory::ZkMemStore,
+    overlay::StateOverlay,
     processor::BatchExecutor,
-    storage::{AccountState, StateStore},
+    receipt::{executed_withdrawals, receipts_root, TxEvent, TxReceipt, TxStatus},
+    recorder::RecordingStore,
+    storage::{AccountState, ProvableStore, StateStore},
+    witness::WitnessStore,
 };
 
 #[cfg(test)]
 mod tests {
     use {
         super::*,
+        ed25519_dalek::SigningKey,
         std::collections::HashMap,
-        zelana_core::{identity::AccountId, SignedTransaction, TransactionData},
+        zelana_core::{
+            bridge::FIRST_L1_SEQ,
+            crypto::hash_bytes,
+            identity::AccountId,
+            merkle::MultiProofNode,
+            test_utils::{signed_transfer, signed_withdrawal, unsigned_transfer},
+            AccountData, BatchPublicValues, DepositEvent, L2Transaction, MultiProof,
+            SignedTransaction, TransactionData,
+        },
     };
 
     //Mock Store (In-Memory)
@@ -63,12 +84,28 @@ mod tests {
             data: tx_data,
             signature: vec![],
             signer_pubkey: [0u8; 32],
+            privacy_pubkey: None,
         };
 
+        let tx = zelana_core::L2Transaction::Transfer(signed);
         let mut executor = BatchExecutor::new(&mut store);
-        executor
-            .execute(&zelana_core::L2Transaction::Transfer(signed))
-            .unwrap();
+        let receipt = executor.execute(&tx).unwrap();
+
+        // Verify the receipt
+        assert!(receipt.is_success());
+        assert_eq!(receipt.tx_hash, tx.hash());
+        assert_eq!(receipt.index, 0);
+        assert_eq!(receipt.balance_deltas.len(), 2);
+        assert_eq!(receipt.balance_deltas[0].account, alice);
+        assert_eq!(receipt.balance_deltas[0].after, 50);
+        assert_eq!(
+            receipt.events,
+            vec![TxEvent::Transfer {
+                from: alice,
+                to: bob,
+                amount: 50
+            }]
+        );
 
         // Verify Alice
         let alice_state = store.get_account(&alice).unwrap();
@@ -79,4 +116,371 @@ mod tests {
         let bob_state = store.get_account(&bob).unwrap();
         assert_eq!(bob_state.balance, 50);
     }
+
+    #[test]
+    fn test_failed_transfer_only_consumes_nonce() {
+        let alice = AccountId([1u8; 32]);
+        let bob = AccountId([2u8; 32]);
+
+        let mut state = HashMap::new();
+        state.insert(
+            alice,
+            AccountData {
+                balance: 100,
+                nonce: 0,
+            },
+        );
+        state.insert(
+            bob,
+            AccountData {
+                balance: u64::MAX,
+                nonce: 0,
+            },
+        );
+        let mut store = ZkMemStore::new(state.clone());
+
+        // The sender is debited before the recipient's credit overflows
+        let tx = unsigned_transfer(alice, bob, 10, 0);
+        let mut executor = BatchExecutor::new(&mut store);
+        let receipt = executor.execute(&tx).unwrap();
+        assert_eq!(receipt.status, TxStatus::Failed(ExecutionError::Overflow));
+        assert_eq!(ExecutionError::Overflow.code(), 3);
+        assert!(receipt.balance_deltas.is_empty());
+        assert!(receipt.events.is_empty());
+
+        // Replaying it is rejected outright and doesn't take an index
+        assert_eq!(
+            executor.execute(&tx).unwrap_err(),
+            ExecutionError::NonceMismatch {
+                expected: 1,
+                got: 0
+            }
+        );
+
+        // Only the nonce moved
+        let alice_state = store.get_account(&alice).unwrap();
+        assert_eq!(alice_state.balance, 100);
+        assert_eq!(alice_state.nonce, 1);
+        state.get_mut(&alice).unwrap().nonce = 1;
+        assert_eq!(store.compute_root(), ZkMemStore::new(state).compute_root());
+    }
+
+    #[test]
+    fn test_deposits_apply_once_in_l1_order() {
+        let alice = AccountId([1u8; 32]);
+        let mut store = MockStore {
+            accounts: HashMap::new(),
+        };
+        store
+            .set_account(
+                alice,
+                AccountState {
+                    balance: u64::MAX - 10,
+                    nonce: 0,
+                },
+            )
+            .unwrap();
+        let deposit = |amount, l1_seq| {
+            L2Transaction::Deposit(DepositEvent {
+                to: alice,
+                amount,
+                l1_seq,
+            })
+        };
+
+        let mut executor = BatchExecutor::new(&mut store).after_deposit(Some(4));
+        // A replayed deposit and one after a gap are both rejected
+        assert_eq!(
+            executor.execute(&deposit(1, 4)),
+            Err(ExecutionError::DepositOutOfOrder {
+                expected: 5,
+                got: 4
+            })
+        );
+        assert_eq!(
+            executor.execute(&deposit(1, 6)),
+            Err(ExecutionError::DepositOutOfOrder {
+                expected: 5,
+                got: 6
+            })
+        );
+
+        assert!(executor.execute(&deposit(5, 5)).unwrap().is_success());
+        // A deposit that can't be credited is included as failed and uses up its l1_seq
+        assert_eq!(
+            executor.execute(&deposit(100, 6)).unwrap().status,
+            TxStatus::Failed(ExecutionError::Overflow)
+        );
+        assert_eq!(executor.l1_seq(), Some(6));
+        assert!(executor.execute(&deposit(1, 7)).unwrap().is_success());
+        assert_eq!(store.get_account(&alice).unwrap().balance, u64::MAX - 4);
+
+        // The very first deposit is the bridge's first l1_seq
+        let mut fresh = MockStore {
+            accounts: HashMap::new(),
+        };
+        let mut executor = BatchExecutor::new(&mut fresh);
+        assert_eq!(
+            executor.execute(&deposit(1, FIRST_L1_SEQ + 1)),
+            Err(ExecutionError::DepositOutOfOrder {
+                expected: FIRST_L1_SEQ,
+                got: FIRST_L1_SEQ + 1
+            })
+        );
+        assert!(executor.execute(&deposit(1, FIRST_L1_SEQ)).is_ok());
+        assert_eq!(executor.l1_seq(), Some(FIRST_L1_SEQ));
+    }
+
+    #[test]
+    fn test_witness_store_matches_full_state() {
+        let alice = AccountId([1u8; 32]);
+        let bob = AccountId([2u8; 32]);
+
+        // Full state: Alice plus unrelated accounts the batch never touches
+        let mut state = HashMap::new();
+        state.insert(
+            alice,
+            AccountData {
+                balance: 100,
+                nonce: 0,
+            },
+        );
+        for i in 10..40u8 {
+            state.insert(
+                AccountId([i; 32]),
+                AccountData {
+                    balance: i as u64,
+                    nonce: 1,
+                },
+            );
+        }
+        let mut full = ZkMemStore::new(state);
+        let pre_root = full.compute_root();
+
+        let (accounts, proof) = full.witness(&[alice, bob]);
+        let mut partial = WitnessStore::new(pre_root, accounts, &proof).unwrap();
+
+        let tx = unsigned_transfer(alice, bob, 30, 0);
+        BatchExecutor::new(&mut full).execute(&tx).unwrap();
+        BatchExecutor::new(&mut partial).execute(&tx).unwrap();
+
+        assert_eq!(partial.compute_root(), full.compute_root());
+
+        // Accounts outside the witness are unreachable
+        assert!(partial.get_account(&AccountId([10u8; 32])).is_err());
+
+        // A witness that lies about a balance is rejected
+        let (mut accounts, proof) = full.witness(&[alice]);
+        accounts[0].data.balance += 1;
+        assert!(WitnessStore::new(full.compute_root(), accounts, &proof).is_err());
+
+        // A proof can't pass the whole state off as one neighbour leaf or one pruned
+        // subtree to make Alice look empty
+        let root = full.compute_root();
+        let (mut accounts, _) = full.witness(&[alice]);
+        accounts[0].data = AccountData::default();
+        let neighbour = MultiProof {
+            nodes: vec![MultiProofNode::Leaf(
+                AccountId([10u8; 32]),
+                AccountData {
+                    balance: 10,
+                    nonce: 1,
+                },
+            )],
+        };
+        assert!(WitnessStore::new(root, accounts.clone(), &neighbour).is_err());
+        let (_, real) = full.witness(&[]);
+        assert!(matches!(real.nodes[..], [MultiProofNode::Pruned(..)]));
+        assert!(WitnessStore::new(root, accounts, &real).is_err());
+    }
+
+    #[test]
+    fn test_recording_store_produces_provable_batch() {
+        let alice = AccountId([1u8; 32]);
+        let bob = AccountId([2u8; 32]);
+        let carol = AccountId([3u8; 32]);
+
+        let mut state = HashMap::new();
+        state.insert(
+            alice,
+            AccountData {
+                balance: 100,
+                nonce: 0,
+            },
+        );
+        for i in 10..40u8 {
+            state.insert(
+                AccountId([i; 32]),
+                AccountData {
+                    balance: 1,
+                    nonce: 0,
+                },
+            );
+        }
+
+        let mut recorder = RecordingStore::new(ZkMemStore::new(state), 1, 1, None).unwrap();
+        recorder
+            .execute(unsigned_transfer(alice, bob, 40, 0))
+            .unwrap();
+        recorder
+            .execute(unsigned_transfer(bob, carol, 15, 0))
+            .unwrap();
+        // Failed transactions stay in the batch...
+        assert_eq!(
+            recorder
+                .execute(unsigned_transfer(alice, bob, 1_000, 1))
+                .unwrap()
+                .status,
+            TxStatus::Failed(ExecutionError::InsufficientFunds {
+                balance: 60,
+                needed: 1_000
+            })
+        );
+        // ...rejected ones are left out
+        assert!(recorder
+            .execute(unsigned_transfer(alice, bob, 1, 1))
+            .is_err());
+
+        let recorded_receipts = recorder.receipts().to_vec();
+        let (input, store) = recorder.finish().unwrap();
+        assert_eq!(input.transactions.len(), 3);
+        assert_eq!(input.witness_accounts.len(), 3);
+
+        // Replaying the recorded batch on the witness alone lands on the same root
+        let mut partial = WitnessStore::new(
+            input.pre_state_root,
+            input.witness_accounts,
+            &input.witness_proof,
+        )
+        .unwrap();
+        let mut executor = BatchExecutor::new(&mut partial);
+        let receipts: Vec<TxReceipt> = input
+            .transactions
+            .iter()
+            .map(|tx| executor.execute(tx).unwrap())
+            .collect();
+        assert_eq!(partial.compute_root(), store.compute_root());
+        assert_eq!(store.get_account(&carol).unwrap().balance, 15);
+
+        // ...and to the same receipts
+        assert_eq!(receipts, recorded_receipts);
+        assert_eq!(receipts[1].index, 1);
+        assert_eq!(store.get_account(&alice).unwrap().nonce, 2);
+    }
+
+    /// Xorshift, so the randomized test is reproducible and needs no extra dependency.
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: u64) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0 % n
+        }
+    }
+
+    /// A signed transfer or withdrawal from one of `keys`' L1-mapped accounts.
+    fn random_user_tx(
+        rng: &mut Rng,
+        keys: &[SigningKey],
+        from: usize,
+        nonce: u64,
+    ) -> L2Transaction {
+        let amount = rng.below(600);
+        if rng.below(3) == 0 {
+            signed_withdrawal(&keys[from], [from as u8; 32], amount, nonce)
+        } else {
+            let to = rng.below(keys.len() as u64) as usize;
+            let to = AccountId(keys[to].verifying_key().to_bytes());
+            signed_transfer(&keys[from], to, amount, nonce)
+        }
+    }
+
+    #[test]
+    fn test_host_and_guest_agree_on_random_batches() {
+        let keys: Vec<SigningKey> = (1..=6u8)
+            .map(|i| SigningKey::from_bytes(&[i; 32]))
+            .collect();
+
+        for seed in 1..=25u64 {
+            let mut rng = Rng(seed);
+
+            // Most users start funded, plus unrelated accounts the batch never touches
+            let mut state = HashMap::new();
+            for key in &keys[1..] {
+                state.insert(
+                    AccountId(key.verifying_key().to_bytes()),
+                    AccountData {
+                        balance: rng.below(1_000),
+                        nonce: 0,
+                    },
+                );
+            }
+            for i in 0..rng.below(40) {
+                state.insert(
+                    AccountId(hash_bytes(&i.to_le_bytes())),
+                    AccountData {
+                        balance: 1,
+                        nonce: 0,
+                    },
+                );
+            }
+
+            // Host: the Sequencer's path over the full state
+            let mut recorder =
+                RecordingStore::new(ZkMemStore::new(state), seed, 1, Some(0)).unwrap();
+            let mut nonces = vec![0u64; keys.len()];
+            let mut l1_seq = 0;
+            for _ in 0..=rng.below(30) {
+                let from = rng.below(keys.len() as u64) as usize;
+                if rng.below(4) == 0 {
+                    l1_seq += 1;
+                    let deposit = DepositEvent {
+                        to: AccountId(keys[from].verifying_key().to_bytes()),
+                        amount: rng.below(500),
+                        l1_seq,
+                    };
+                    recorder.execute(L2Transaction::Deposit(deposit)).unwrap();
+                    continue;
+                }
+
+                // Now and then a stale nonce, which the batch must leave out
+                let nonce = nonces[from] + rng.below(8) / 7;
+                let tx = random_user_tx(&mut rng, &keys, from, nonce);
+                if recorder.execute(tx).is_ok() {
+                    nonces[from] += 1;
+                }
+            }
+
+            let recorded = recorder.receipts().to_vec();
+            let (input, store) = recorder.finish().unwrap();
+            let expected = BatchPublicValues::new(
+                &input,
+                store.compute_root(),
+                &executed_withdrawals(&input.transactions, &recorded),
+            );
+
+            // Guest: the proven path over the witness alone
+            let output = execute_batch(input.clone()).unwrap();
+            assert_eq!(output.public_values.to_bytes(), expected.to_bytes());
+            assert_eq!(output.receipts, recorded);
+
+            // A tampered batch doesn't prove
+            let mut tampered = input;
+            if let Some(index) = tampered
+                .transactions
+                .iter()
+                .position(|tx| matches!(tx, L2Transaction::Transfer(_)))
+            {
+                if let L2Transaction::Transfer(tx) = &mut tampered.transactions[index] {
+                    tx.data.amount += 1;
+                }
+                assert!(matches!(
+                    execute_batch(tampered),
+                    Err(BatchError::Unauthorized { index: i, .. }) if i == index
+                ));
+            }
+        }
+    }
 }
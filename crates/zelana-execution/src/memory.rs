@@ -1,61 +1,79 @@
 use {
-    crate::{AccountState, StateStore},
+    crate::{AccountState, ProvableStore, StateStore},
     anyhow::Result,
-    blake3::Hasher,
     std::collections::HashMap,
-    zelana_core::{prover::AccountData, AccountId},
+    zelana_core::{
+        prover::AccountData, AccountId, AccountWitness, MerkleProof, MultiProof, SparseMerkleTree,
+    },
 };
 
-/// A lightweight, verifiable state store.
-/// Used by the Prover (Guest) AND the Batch Generator (Host).
+/// A lightweight, verifiable state store holding the full state in memory.
+/// Used by the Batch Generator (Host); the Prover (Guest) uses `WitnessStore`.
 pub struct ZkMemStore {
-    accounts: HashMap<AccountId, AccountState>,
+    tree: SparseMerkleTree,
 }
 
 impl ZkMemStore {
     /// Initialize from the witness data provided by the Sequencer.
     pub fn new(witness: HashMap<AccountId, AccountData>) -> Self {
-        let mut accounts = HashMap::new();
+        let mut tree = SparseMerkleTree::new();
         for (id, data) in witness {
-            accounts.insert(
-                id,
-                AccountState {
-                    balance: data.balance,
-                    nonce: data.nonce,
-                },
-            );
+            tree.insert(id, data);
         }
-        Self { accounts }
+        Self { tree }
     }
 
     /// Computes the cryptographic commitment (Root) of the current state.
-    /// Logic: Hash( Sort( [ID || Balance || Nonce] ) )
+    /// This is the root of the sparse Merkle tree keyed by `AccountId`.
     pub fn compute_root(&self) -> [u8; 32] {
-        // 1. Collect all entries
-        let mut entries: Vec<(&AccountId, &AccountState)> = self.accounts.iter().collect();
-
-        // 2. Sort by ID (Critical for determinism)
-        entries.sort_by_key(|(id, _)| id.0);
-
-        // 3. Hash them all
-        let mut hasher = Hasher::new();
-        for (id, state) in entries {
-            hasher.update(&id.0);
-            hasher.update(&state.balance.to_le_bytes());
-            hasher.update(&state.nonce.to_le_bytes());
-        }
+        self.tree.root()
+    }
+
+    /// Inclusion (or exclusion) proof for a single account against `compute_root()`.
+    pub fn prove(&self, id: &AccountId) -> MerkleProof {
+        self.tree.prove(id)
+    }
+
+    /// Builds the witness for a batch touching `ids`: their current state plus a multiproof.
+    pub fn witness(&self, ids: &[AccountId]) -> (Vec<AccountWitness>, MultiProof) {
+        let mut ids = ids.to_vec();
+        ids.sort_by_key(|id| id.0);
+        ids.dedup();
+
+        let accounts = ids
+            .iter()
+            .map(|id| AccountWitness {
+                id: *id,
+                data: self.tree.get(id).cloned().unwrap_or_default(),
+            })
+            .collect();
 
-        hasher.finalize().into()
+        (accounts, self.tree.multiproof(&ids))
     }
 }
 
 impl StateStore for ZkMemStore {
     fn get_account(&self, id: &AccountId) -> Result<AccountState> {
-        Ok(self.accounts.get(id).cloned().unwrap_or_default())
+        Ok(self
+            .tree
+            .get(id)
+            .cloned()
+            .map(Into::into)
+            .unwrap_or_default())
     }
 
     fn set_account(&mut self, id: AccountId, state: AccountState) -> Result<()> {
-        self.accounts.insert(id, state);
+        self.tree.insert(id, state.into());
         Ok(())
     }
 }
+
+impl ProvableStore for ZkMemStore {
+    fn state_root(&self) -> Result<[u8; 32]> {
+        Ok(self.tree.root())
+    }
+
+    fn multiproof(&self, ids: &[AccountId]) -> Result<MultiProof> {
+        Ok(self.tree.multiproof(ids))
+    }
+}
@@ -0,0 +1,67 @@
+use {
+    crate::{
+        receipt::BalanceDelta,
+        storage::{AccountState, StateStore},
+    },
+    anyhow::Result,
+    std::collections::HashMap,
+    zelana_core::AccountId,
+};
+
+/// Uncommitted writes layered over a store.
+///
+/// Reads see the pending writes first and fall through to the base store. Nothing
+/// reaches the base store until `commit`; `rollback` (or dropping the overlay)
+/// discards every pending write.
+pub struct StateOverlay<'a, S: StateStore> {
+    base: &'a mut S,
+    writes: HashMap<AccountId, AccountState>,
+}
+
+impl<'a, S: StateStore> StateOverlay<'a, S> {
+    pub fn begin(base: &'a mut S) -> Self {
+        Self {
+            base,
+            writes: HashMap::new(),
+        }
+    }
+
+    /// Balance changes of the pending writes against the base store, sorted by account.
+    pub fn balance_deltas(&self) -> Result<Vec<BalanceDelta>> {
+        let mut deltas = Vec::new();
+        for (id, state) in &self.writes {
+            let before = self.base.get_account(id)?.balance;
+            if before != state.balance {
+                deltas.push(BalanceDelta {
+                    account: *id,
+                    before,
+                    after: state.balance,
+                });
+            }
+        }
+        deltas.sort_by_key(|delta| delta.account.0);
+        Ok(deltas)
+    }
+
+    /// Applies the pending writes to the base store.
+    pub fn commit(self) -> Result<()> {
+        self.base.set_accounts(self.writes.into_iter().collect())
+    }
+
+    /// Discards the pending writes.
+    pub fn rollback(self) {}
+}
+
+impl<S: StateStore> StateStore for StateOverlay<'_, S> {
+    fn get_account(&self, id: &AccountId) -> Result<AccountState> {
+        match self.writes.get(id) {
+            Some(state) => Ok(state.clone()),
+            None => self.base.get_account(id),
+        }
+    }
+
+    fn set_account(&mut self, id: AccountId, state: AccountState) -> Result<()> {
+        self.writes.insert(id, state);
+        Ok(())
+    }
+}
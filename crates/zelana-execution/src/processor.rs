@@ -1,94 +1,223 @@
 use {
-    crate::storage::StateStore,
-    anyhow::{bail, Result},
-    zelana_core::{transaction::WithdrawRequest, DepositEvent, L2Transaction, SignedTransaction},
+    crate::{
+        error::ExecutionError,
+        overlay::StateOverlay,
+        receipt::{TxEvent, TxReceipt, TxStatus},
+        storage::StateStore,
+    },
+    zelana_core::{
+        bridge::FIRST_L1_SEQ, transaction::WithdrawRequest, DepositEvent, L2Transaction,
+        SignedTransaction,
+    },
 };
 
 pub struct BatchExecutor<'a, S: StateStore> {
     store: &'a mut S,
+    /// Index the next executed transaction gets in its batch.
+    next_index: u32,
+    /// `l1_seq` of the last deposit applied (`None` before the first one ever).
+    l1_seq: Option<u64>,
 }
 
 impl<'a, S: StateStore> BatchExecutor<'a, S> {
     pub fn new(store: &'a mut S) -> Self {
-        Self { store }
+        Self::starting_at(store, 0)
     }
-    /// Entry point for applying a generic L2 Transaction.
-    pub fn execute(&mut self, tx: &L2Transaction) -> Result<()> {
-        match tx {
-            L2Transaction::Transfer(signed_tx) => self.execute_transfer(signed_tx),
-            L2Transaction::Deposit(deposit) => self.execute_deposit(deposit),
-            L2Transaction::Withdraw(req) => self.execute_withdraw(req),
+
+    /// An executor continuing a batch that already holds `next_index` transactions.
+    pub fn starting_at(store: &'a mut S, next_index: u32) -> Self {
+        Self {
+            store,
+            next_index,
+            l1_seq: None,
         }
     }
-    fn execute_transfer(&mut self, tx: &SignedTransaction) -> Result<()> {
-        //Verify Signature
-        // In the ZKVM, we assume signature checked by the main loop witness verification.
 
-        let from_id = tx.data.from;
-        let to_id = tx.data.to;
-        let amount = tx.data.amount;
-        let nonce = tx.data.nonce;
+    /// Continues deposits after `l1_seq`, the last one applied. The next deposit must
+    /// have `l1_seq + 1` (or `FIRST_L1_SEQ` if `None`).
+    pub fn after_deposit(mut self, l1_seq: Option<u64>) -> Self {
+        self.l1_seq = l1_seq;
+        self
+    }
 
-        //Load Sender
-        let mut sender = self.store.get_account(&from_id)?;
+    /// `l1_seq` of the last deposit applied so far.
+    pub fn l1_seq(&self) -> Option<u64> {
+        self.l1_seq
+    }
 
-        //Checks
-        if sender.nonce != nonce {
-            bail!("Nonce mismatch: expected {}, got {}", sender.nonce, nonce);
-        }
-        if sender.balance < amount {
-            bail!(
-                "Insufficient funds: balance {}, needed {}",
-                sender.balance,
-                amount
-            );
+    /// Entry point for applying a generic L2 Transaction.
+    /// The transaction runs on an overlay, so a failure leaves the store untouched.
+    ///
+    /// A failure that `ExecutionError::is_includable` still yields a receipt, with status
+    /// `Failed`; the only state change is the sender's nonce bump (or, for a deposit,
+    /// its `l1_seq`). Any other failure is returned as an error and doesn't use up an
+    /// index.
+    pub fn execute(&mut self, tx: &L2Transaction) -> Result<TxReceipt, ExecutionError> {
+        // A deposit is applied exactly once
+        if let L2Transaction::Deposit(deposit) = tx {
+            let expected = self.l1_seq.map_or(FIRST_L1_SEQ, |seq| seq + 1);
+            if deposit.l1_seq != expected {
+                return Err(ExecutionError::DepositOutOfOrder {
+                    expected,
+                    got: deposit.l1_seq,
+                });
+            }
         }
 
-        //Update Sender
-        sender.balance -= amount;
-        sender.nonce += 1;
-        self.store.set_account(from_id, sender)?;
+        let mut overlay = StateOverlay::begin(&mut *self.store);
+
+        let result = match tx {
+            L2Transaction::Transfer(signed_tx) => execute_transfer(&mut overlay, signed_tx),
+            L2Transaction::Deposit(deposit) => execute_deposit(&mut overlay, deposit),
+            L2Transaction::Withdraw(req) => execute_withdraw(&mut overlay, req),
+        };
 
-        //Update Recipient
-        let mut recipient = self.store.get_account(&to_id)?;
-        recipient.balance = recipient
-            .balance
-            .checked_add(amount)
-            .ok_or_else(|| anyhow::anyhow!("Overflow in recipient balance"))?;
-        self.store.set_account(to_id, recipient)?;
+        let (status, events) = match result {
+            Ok(event) => (TxStatus::Success, vec![event]),
+            // A deposit that can't be credited fails the same way every time, so it is
+            // included too: its l1_seq is used up and later deposits don't wait on it
+            Err(e) if e.is_includable() => {
+                overlay.rollback();
+                overlay = StateOverlay::begin(&mut *self.store);
+                consume_nonce(&mut overlay, tx)?;
+                (TxStatus::Failed(e), vec![])
+            }
+            Err(e) => {
+                overlay.rollback();
+                return Err(e);
+            }
+        };
 
-        Ok(())
+        let receipt = TxReceipt {
+            tx_hash: tx.hash(),
+            index: self.next_index,
+            status,
+            balance_deltas: overlay.balance_deltas()?,
+            events,
+        };
+        overlay.commit()?;
+
+        if let L2Transaction::Deposit(deposit) = tx {
+            self.l1_seq = Some(deposit.l1_seq);
+        }
+        self.next_index += 1;
+        Ok(receipt)
     }
+}
+
+/// Bumps the nonce of a failed transaction's sender, so it can't be replayed.
+/// A failed deposit has no sender; its `l1_seq` is used up instead.
+fn consume_nonce<S: StateStore>(store: &mut S, tx: &L2Transaction) -> Result<(), ExecutionError> {
+    let from = match tx {
+        L2Transaction::Transfer(signed_tx) => signed_tx.data.from,
+        L2Transaction::Withdraw(req) => req.data.from,
+        L2Transaction::Deposit(_) => return Ok(()),
+    };
+
+    let mut sender = store.get_account(&from)?;
+    sender.nonce += 1;
+    store.set_account(from, sender)?;
+    Ok(())
+}
 
-    fn execute_deposit(&mut self, deposit: &DepositEvent) -> Result<()> {
-        // Deposits are authoritative "Mint" events from L1.
-        // We do not check nonces or signatures (L1 Bridge did that).
-        let mut account = self.store.get_account(&deposit.to)?;
+fn execute_transfer<S: StateStore>(
+    store: &mut S,
+    tx: &SignedTransaction,
+) -> Result<TxEvent, ExecutionError> {
+    // Authorization is checked before execution (`L2Transaction::verify_authorization`).
 
-        account.balance = account
-            .balance
-            .checked_add(deposit.amount)
-            .ok_or_else(|| anyhow::anyhow!("Overflow in deposit"))?;
+    let from_id = tx.data.from;
+    let to_id = tx.data.to;
+    let amount = tx.data.amount;
+    let nonce = tx.data.nonce;
 
-        self.store.set_account(deposit.to, account)?;
-        Ok(())
+    //Load Sender
+    let mut sender = store.get_account(&from_id)?;
+
+    //Checks
+    if sender.nonce != nonce {
+        return Err(ExecutionError::NonceMismatch {
+            expected: sender.nonce,
+            got: nonce,
+        });
+    }
+    if sender.balance < amount {
+        return Err(ExecutionError::InsufficientFunds {
+            balance: sender.balance,
+            needed: amount,
+        });
     }
 
-    fn execute_withdraw(&mut self, req: &WithdrawRequest) -> Result<()> {
-        let mut sender = self.store.get_account(&req.from)?;
+    //Update Sender
+    sender.balance -= amount;
+    sender.nonce += 1;
+    store.set_account(from_id, sender)?;
 
-        if sender.nonce != req.nonce {
-            bail!("Nonce mismatch on withdraw");
-        }
-        if sender.balance < req.amount {
-            bail!("Insufficient funds for withdraw");
-        }
+    //Update Recipient
+    let mut recipient = store.get_account(&to_id)?;
+    recipient.balance = recipient
+        .balance
+        .checked_add(amount)
+        .ok_or(ExecutionError::Overflow)?;
+    store.set_account(to_id, recipient)?;
+
+    Ok(TxEvent::Transfer {
+        from: from_id,
+        to: to_id,
+        amount,
+    })
+}
+
+fn execute_deposit<S: StateStore>(
+    store: &mut S,
+    deposit: &DepositEvent,
+) -> Result<TxEvent, ExecutionError> {
+    // Deposits are authoritative "Mint" events from L1.
+    // We do not check nonces or signatures (L1 Bridge did that).
+    let mut account = store.get_account(&deposit.to)?;
 
-        // Burn funds on L2
-        sender.balance -= req.amount;
-        sender.nonce += 1;
+    account.balance = account
+        .balance
+        .checked_add(deposit.amount)
+        .ok_or(ExecutionError::Overflow)?;
 
-        self.store.set_account(req.from, sender)?;
-        Ok(())
+    store.set_account(deposit.to, account)?;
+    Ok(TxEvent::Deposit {
+        to: deposit.to,
+        amount: deposit.amount,
+        l1_seq: deposit.l1_seq,
+    })
+}
+
+fn execute_withdraw<S: StateStore>(
+    store: &mut S,
+    req: &WithdrawRequest,
+) -> Result<TxEvent, ExecutionError> {
+    // Authorization is checked before execution (`L2Transaction::verify_authorization`).
+    let req = &req.data;
+    let mut sender = store.get_account(&req.from)?;
+
+    if sender.nonce != req.nonce {
+        return Err(ExecutionError::NonceMismatch {
+            expected: sender.nonce,
+            got: req.nonce,
+        });
     }
+    if sender.balance < req.amount {
+        return Err(ExecutionError::InsufficientFunds {
+            balance: sender.balance,
+            needed: req.amount,
+        });
+    }
+
+    // Burn funds on L2
+    sender.balance -= req.amount;
+    sender.nonce += 1;
+
+    store.set_account(req.from, sender)?;
+    Ok(TxEvent::Withdraw {
+        from: req.from,
+        to_l1_address: req.to_l1_address,
+        amount: req.amount,
+    })
 }
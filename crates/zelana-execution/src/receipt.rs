@@ -0,0 +1,88 @@
+use {
+    crate::error::ExecutionError,
+    wincode::{SchemaRead, SchemaWrite},
+    zelana_core::{
+        crypto::{hash_bytes, to_bytes},
+        merkle::list_root,
+        AccountId, L2Transaction, WithdrawData,
+    },
+};
+
+/// The outcome of one transaction in a batch.
+#[derive(Debug, Clone, PartialEq, Eq, SchemaRead, SchemaWrite)]
+pub struct TxReceipt {
+    /// `L2Transaction::hash` of the transaction.
+    pub tx_hash: [u8; 32],
+    /// Position of the transaction in its batch.
+    pub index: u32,
+    pub status: TxStatus,
+    /// Balance changes, sorted by account.
+    pub balance_deltas: Vec<BalanceDelta>,
+    pub events: Vec<TxEvent>,
+}
+
+impl TxReceipt {
+    pub fn is_success(&self) -> bool {
+        self.status == TxStatus::Success
+    }
+
+    /// BLAKE3 of the serialized receipt.
+    pub fn hash(&self) -> [u8; 32] {
+        hash_bytes(&to_bytes(self))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, SchemaRead, SchemaWrite)]
+pub enum TxStatus {
+    Success,
+    Failed(ExecutionError),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, SchemaRead, SchemaWrite)]
+pub struct BalanceDelta {
+    pub account: AccountId,
+    pub before: u64,
+    pub after: u64,
+}
+
+/// What a transaction did, for clients and indexers.
+#[derive(Debug, Clone, PartialEq, Eq, SchemaRead, SchemaWrite)]
+pub enum TxEvent {
+    Transfer {
+        from: AccountId,
+        to: AccountId,
+        amount: u64,
+    },
+    Deposit {
+        to: AccountId,
+        amount: u64,
+        l1_seq: u64,
+    },
+    Withdraw {
+        from: AccountId,
+        to_l1_address: [u8; 32],
+        amount: u64,
+    },
+}
+
+/// `list_root` over the receipt hashes, in batch order.
+pub fn receipts_root(receipts: &[TxReceipt]) -> [u8; 32] {
+    let hashes: Vec<[u8; 32]> = receipts.iter().map(TxReceipt::hash).collect();
+    list_root(&hashes)
+}
+
+/// The withdrawals among `transactions` that executed successfully, in batch order.
+/// `receipts` holds one receipt per transaction.
+pub fn executed_withdrawals(
+    transactions: &[L2Transaction],
+    receipts: &[TxReceipt],
+) -> Vec<WithdrawData> {
+    transactions
+        .iter()
+        .zip(receipts)
+        .filter_map(|(tx, receipt)| match tx {
+            L2Transaction::Withdraw(req) if receipt.is_success() => Some(req.data.clone()),
+            _ => None,
+        })
+        .collect()
+}
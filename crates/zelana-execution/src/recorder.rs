@@ -0,0 +1,162 @@
+use {
+    crate::{
+        error::ExecutionError,
+        receipt::TxReceipt,
+        storage::{AccountState, ProvableStore, StateStore},
+        BatchExecutor,
+    },
+    anyhow::{bail, Result},
+    std::{cell::RefCell, collections::HashMap},
+    zelana_core::{AccountId, AccountWitness, BatchInput, L2Transaction},
+};
+
+/// A state store decorator that records everything a batch touches.
+///
+/// Reads fall through to the inner store and remember the first value seen for each
+/// account. Writes are held back, so the inner store keeps the pre-state until
+/// `finish`, which proves the touched accounts against it and then applies the writes.
+pub struct RecordingStore<S: ProvableStore> {
+    inner: S,
+    batch_height: u64,
+    chain_id: u64,
+    /// `l1_seq` of the last deposit applied before the batch.
+    prev_l1_seq: Option<u64>,
+    /// `l1_seq` of the last deposit applied so far.
+    l1_seq: Option<u64>,
+    pre_state_root: [u8; 32],
+    /// State of every touched account before the batch (the witness).
+    pre_state: RefCell<HashMap<AccountId, AccountState>>,
+    /// State written by the batch so far.
+    writes: HashMap<AccountId, AccountState>,
+    transactions: Vec<L2Transaction>,
+    receipts: Vec<TxReceipt>,
+}
+
+impl<S: ProvableStore> RecordingStore<S> {
+    /// Starts recording batch `batch_height` of chain `chain_id` on top of `inner`,
+    /// whose last applied deposit is `prev_l1_seq`.
+    pub fn new(
+        inner: S,
+        batch_height: u64,
+        chain_id: u64,
+        prev_l1_seq: Option<u64>,
+    ) -> Result<Self> {
+        Ok(Self {
+            pre_state_root: inner.state_root()?,
+            inner,
+            batch_height,
+            chain_id,
+            prev_l1_seq,
+            l1_seq: prev_l1_seq,
+            pre_state: RefCell::new(HashMap::new()),
+            writes: HashMap::new(),
+            transactions: Vec::new(),
+            receipts: Vec::new(),
+        })
+    }
+
+    /// Executes `tx` on top of the recorded state and appends it to the batch.
+    /// Transactions rejected with an error are not appended; ones that fail with a
+    /// `Failed` receipt are, as the Guest replays them the same way.
+    pub fn execute(&mut self, tx: L2Transaction) -> Result<TxReceipt, ExecutionError> {
+        let index = self.transactions.len() as u32;
+        let l1_seq = self.l1_seq;
+        let mut executor = BatchExecutor::starting_at(self, index).after_deposit(l1_seq);
+        let receipt = executor.execute(&tx)?;
+        self.l1_seq = executor.l1_seq();
+        self.transactions.push(tx);
+        self.receipts.push(receipt.clone());
+        Ok(receipt)
+    }
+
+    /// `l1_seq` of the last deposit applied, including the ones in this batch.
+    pub fn l1_seq(&self) -> Option<u64> {
+        self.l1_seq
+    }
+
+    pub fn transactions(&self) -> &[L2Transaction] {
+        &self.transactions
+    }
+
+    /// Receipts of the recorded transactions, in batch order.
+    pub fn receipts(&self) -> &[TxReceipt] {
+        &self.receipts
+    }
+
+    /// Builds the `BatchInput` for everything executed so far, proving the touched
+    /// accounts against the (still unmodified) inner store.
+    pub fn batch_input(&self) -> Result<BatchInput> {
+        if self.inner.state_root()? != self.pre_state_root {
+            bail!("Inner store was modified while recording a batch");
+        }
+
+        let mut witness: Vec<(AccountId, AccountState)> = self
+            .pre_state
+            .borrow()
+            .iter()
+            .map(|(id, state)| (*id, state.clone()))
+            .collect();
+        witness.sort_by_key(|(id, _)| id.0);
+
+        let ids: Vec<AccountId> = witness.iter().map(|(id, _)| *id).collect();
+        let witness_proof = self.inner.multiproof(&ids)?;
+
+        Ok(BatchInput {
+            batch_height: self.batch_height,
+            chain_id: self.chain_id,
+            prev_l1_seq: self.prev_l1_seq,
+            pre_state_root: self.pre_state_root,
+            transactions: self.transactions.clone(),
+            witness_accounts: witness
+                .into_iter()
+                .map(|(id, state)| AccountWitness {
+                    id,
+                    data: state.into(),
+                })
+                .collect(),
+            witness_proof,
+        })
+    }
+
+    /// The batch's writes, not yet applied, for callers that persist them together
+    /// with other data.
+    pub fn writes(&self) -> Vec<(AccountId, AccountState)> {
+        self.writes
+            .iter()
+            .map(|(id, state)| (*id, state.clone()))
+            .collect()
+    }
+
+    /// Builds the `BatchInput`, then applies the batch's writes to the inner store
+    /// and hands it back.
+    pub fn finish(self) -> Result<(BatchInput, S)> {
+        let input = self.batch_input()?;
+        let mut inner = self.inner;
+        inner.set_accounts(self.writes.into_iter().collect())?;
+        Ok((input, inner))
+    }
+}
+
+impl<S: ProvableStore> StateStore for RecordingStore<S> {
+    fn get_account(&self, id: &AccountId) -> Result<AccountState> {
+        if let Some(state) = self.writes.get(id) {
+            return Ok(state.clone());
+        }
+        if let Some(state) = self.pre_state.borrow().get(id) {
+            return Ok(state.clone());
+        }
+
+        let state = self.inner.get_account(id)?;
+        self.pre_state.borrow_mut().insert(*id, state.clone());
+        Ok(state)
+    }
+
+    fn set_account(&mut self, id: AccountId, state: AccountState) -> Result<()> {
+        // Blind writes still need the old value in the witness.
+        if !self.writes.contains_key(&id) {
+            self.get_account(&id)?;
+        }
+        self.writes.insert(id, state);
+        Ok(())
+    }
+}
@@ -1,7 +1,7 @@
 use {
     anyhow::Result,
     wincode::{SchemaRead, SchemaWrite},
-    zelana_core::AccountId,
+    zelana_core::{AccountData, AccountId, MultiProof},
 };
 
 /// minimal state of user in l2
@@ -11,6 +11,24 @@ pub struct AccountState {
     pub nonce: u64,
 }
 
+impl From<AccountData> for AccountState {
+    fn from(data: AccountData) -> Self {
+        Self {
+            balance: data.balance,
+            nonce: data.nonce,
+        }
+    }
+}
+
+impl From<AccountState> for AccountData {
+    fn from(state: AccountState) -> Self {
+        Self {
+            balance: state.balance,
+            nonce: state.nonce,
+        }
+    }
+}
+
 /// decoupling logic from the db
 pub trait StateStore {
     /// Retrieve an account. Returns Default if not found.
@@ -18,4 +36,22 @@ pub trait StateStore {
 
     /// Update an account's state.
     fn set_account(&mut self, id: AccountId, state: AccountState) -> Result<()>;
+
+    /// Update several accounts together.
+    /// Stores that can write atomically should override this.
+    fn set_accounts(&mut self, updates: Vec<(AccountId, AccountState)>) -> Result<()> {
+        for (id, state) in updates {
+            self.set_account(id, state)?;
+        }
+        Ok(())
+    }
+}
+
+/// A state store that commits to its contents with the Sparse Merkle root.
+pub trait ProvableStore: StateStore {
+    /// The current state root.
+    fn state_root(&self) -> Result<[u8; 32]>;
+
+    /// A multiproof for `ids` against `state_root()`.
+    fn multiproof(&self, ids: &[AccountId]) -> Result<MultiProof>;
 }
@@ -0,0 +1,68 @@
+use {
+    crate::{AccountState, StateStore},
+    anyhow::{anyhow, bail, Result},
+    std::collections::HashMap,
+    zelana_core::{
+        merkle::{account_leaf, PartialTree},
+        AccountData, AccountId, AccountWitness, MultiProof,
+    },
+};
+
+/// A state store holding only the accounts a batch touches, authenticated by a multiproof.
+/// Used by the Prover (Guest): touching any account outside the witness is an error.
+pub struct WitnessStore {
+    accounts: HashMap<AccountId, AccountState>,
+    tree: PartialTree,
+}
+
+impl WitnessStore {
+    /// Rebuilds the witness and checks it against the claimed pre-state root.
+    pub fn new(
+        pre_state_root: [u8; 32],
+        witness: Vec<AccountWitness>,
+        proof: &MultiProof,
+    ) -> Result<Self> {
+        let tree = PartialTree::from_proof(proof)?;
+        if tree.root() != pre_state_root {
+            bail!("Witness proof does not match the pre-state root");
+        }
+
+        let mut accounts = HashMap::new();
+        for AccountWitness { id, data } in witness {
+            if tree.leaf(&id)? != account_leaf(&id, &data) {
+                bail!("Witness data for {:?} does not match the proof", id);
+            }
+            if accounts.insert(id, data.into()).is_some() {
+                bail!("Duplicate witness account {:?}", id);
+            }
+        }
+
+        Ok(Self { accounts, tree })
+    }
+
+    /// Computes the state root after the updates applied so far.
+    pub fn compute_root(&self) -> [u8; 32] {
+        self.tree.root()
+    }
+}
+
+impl StateStore for WitnessStore {
+    fn get_account(&self, id: &AccountId) -> Result<AccountState> {
+        self.accounts
+            .get(id)
+            .cloned()
+            .ok_or_else(|| anyhow!("{:?} is missing from the witness", id))
+    }
+
+    fn set_account(&mut self, id: AccountId, state: AccountState) -> Result<()> {
+        let slot = self
+            .accounts
+            .get_mut(&id)
+            .ok_or_else(|| anyhow!("{:?} is missing from the witness", id))?;
+
+        let data: AccountData = state.clone().into();
+        self.tree.update(&id, account_leaf(&id, &data))?;
+        *slot = state;
+        Ok(())
+    }
+}
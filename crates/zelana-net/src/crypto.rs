@@ -26,9 +26,14 @@ impl EphemeralKeyPair {
 pub struct SessionKeys {
     aead: ChaCha20Poly1305,
     base_iv: [u8; 12],
+    /// Seals the server's replies. Both sides count from zero, so replies under the
+    /// client's key would reuse its nonces.
+    reply_aead: ChaCha20Poly1305,
+    reply_iv: [u8; 12],
     /// We track the sequence number to prevent replay attacks
     tx_counter: u64,
     rx_counter: u64,
+    reply_counter: u64,
 }
 
 impl SessionKeys {
@@ -50,11 +55,20 @@ impl SessionKeys {
         let key = Key::from_slice(&okm[0..32]);
         let iv: [u8; 12] = okm[32..44].try_into().unwrap();
 
+        let mut reply_okm = [0u8; 44];
+        hk.expand(b"zelana-v2-reply", &mut reply_okm)
+            .expect("HKDF expansion failed");
+        let reply_key = Key::from_slice(&reply_okm[0..32]);
+        let reply_iv: [u8; 12] = reply_okm[32..44].try_into().unwrap();
+
         Self {
             aead: ChaCha20Poly1305::new(key),
             base_iv: iv,
+            reply_aead: ChaCha20Poly1305::new(reply_key),
+            reply_iv,
             tx_counter: 0,
             rx_counter: 0,
+            reply_counter: 0,
         }
     }
 
@@ -62,38 +76,66 @@ impl SessionKeys {
     /// Returns: [Nonce (12B) || Ciphertext]
     pub fn encrypt(&mut self, plaintext: &[u8]) -> anyhow::Result<Vec<u8>> {
         self.tx_counter += 1;
-        let nonce = compute_nonce(&self.base_iv, self.tx_counter);
-
-        let ciphertext = self
-            .aead
-            .encrypt(&nonce, plaintext)
-            .map_err(|_| anyhow::anyhow!("Encryption failure"))?;
-
-        // Prepend nonce for the receiver
-        let mut output = Vec::with_capacity(12 + ciphertext.len());
-        output.extend_from_slice(nonce.as_slice());
-        output.extend_from_slice(&ciphertext);
-
-        Ok(output)
+        seal(&self.aead, &self.base_iv, self.tx_counter, plaintext)
     }
 
     /// Decrypts a payload given the nonce provided in the packet.
     /// Note:  verify the nonce > rx_counter.
     pub fn decrypt(&mut self, nonce_bytes: &[u8], ciphertext: &[u8]) -> anyhow::Result<Vec<u8>> {
-        if nonce_bytes.len() != 12 {
-            return Err(anyhow::anyhow!("Invalid nonce length"));
-        }
-        let nonce = Nonce::from_slice(nonce_bytes);
+        open(&self.aead, nonce_bytes, ciphertext)
+    }
 
-        let plaintext = self
-            .aead
-            .decrypt(nonce, ciphertext)
-            .map_err(|_| anyhow::anyhow!("Decryption failure (Bad Key or Mac)"))?;
+    /// Encrypts a server reply under the reply key and increments the reply counter.
+    /// Returns: [Nonce (12B) || Ciphertext]
+    pub fn encrypt_reply(&mut self, plaintext: &[u8]) -> anyhow::Result<Vec<u8>> {
+        self.reply_counter += 1;
+        seal(
+            &self.reply_aead,
+            &self.reply_iv,
+            self.reply_counter,
+            plaintext,
+        )
+    }
 
-        Ok(plaintext)
+    /// Decrypts a server reply sealed by `encrypt_reply`.
+    pub fn decrypt_reply(&self, nonce_bytes: &[u8], ciphertext: &[u8]) -> anyhow::Result<Vec<u8>> {
+        open(&self.reply_aead, nonce_bytes, ciphertext)
     }
 }
 
+fn seal(
+    aead: &ChaCha20Poly1305,
+    base_iv: &[u8; 12],
+    counter: u64,
+    plaintext: &[u8],
+) -> anyhow::Result<Vec<u8>> {
+    let nonce = compute_nonce(base_iv, counter);
+
+    let ciphertext = aead
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| anyhow::anyhow!("Encryption failure"))?;
+
+    // Prepend nonce for the receiver
+    let mut output = Vec::with_capacity(12 + ciphertext.len());
+    output.extend_from_slice(nonce.as_slice());
+    output.extend_from_slice(&ciphertext);
+
+    Ok(output)
+}
+
+fn open(aead: &ChaCha20Poly1305, nonce_bytes: &[u8], ciphertext: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if nonce_bytes.len() != 12 {
+        return Err(anyhow::anyhow!("Invalid nonce length"));
+    }
+    let nonce = Nonce::from_slice(nonce_bytes);
+
+    let plaintext = aead
+        .decrypt(nonce, ciphertext)
+        .map_err(|_| anyhow::anyhow!("Decryption failure (Bad Key or Mac)"))?;
+
+    Ok(plaintext)
+}
+
 /// XOR-based counter nonce generation (WireGuard style).
 fn compute_nonce(base_iv: &[u8; 12], counter: u64) -> Nonce {
     let mut n = *base_iv;
@@ -4,6 +4,8 @@ pub mod protocol;
 pub const KIND_CLIENT_HELLO: u8 = 1;
 pub const KIND_SERVER_HELLO: u8 = 2;
 pub const KIND_APP_DATA: u8 = 3;
+pub const KIND_QUERY: u8 = 4;
+pub const KIND_REPLY: u8 = 5;
 
 ///  packet header size (1 byte kind + 12 bytes nonce).
 pub const HEADER_SIZE: usize = 1 + 12;
@@ -64,4 +66,23 @@ mod tests {
         // The first 12 bytes (nonce) MUST be different
         assert_ne!(&c1[0..12], &c2[0..12]);
     }
+
+    #[test]
+    fn test_replies_use_their_own_key() {
+        let keys = EphemeralKeyPair::generate();
+        let pk = *keys.pk.as_bytes();
+        let mut client = SessionKeys::derive([7u8; 32], &pk, &pk);
+        let mut server = SessionKeys::derive([7u8; 32], &pk, &pk);
+
+        let request = client.encrypt(b"query").unwrap();
+        let reply = server.encrypt_reply(b"reply").unwrap();
+
+        // Both are the first message in their direction, but never share a nonce
+        assert_ne!(&request[0..12], &reply[0..12]);
+        assert_eq!(
+            client.decrypt_reply(&reply[0..12], &reply[12..]).unwrap(),
+            b"reply"
+        );
+        assert!(client.decrypt(&reply[0..12], &reply[12..]).is_err());
+    }
 }
@@ -1,5 +1,5 @@
 use {
-    crate::{KIND_APP_DATA, KIND_CLIENT_HELLO, KIND_SERVER_HELLO},
+    crate::{KIND_APP_DATA, KIND_CLIENT_HELLO, KIND_QUERY, KIND_REPLY, KIND_SERVER_HELLO},
     anyhow::{bail, Result},
 };
 
@@ -15,6 +15,16 @@ pub enum Packet<'a> {
         nonce: &'a [u8; 12],
         ciphertext: &'a [u8],
     },
+    /// A read-only request, encrypted like `AppData`.
+    Query {
+        nonce: &'a [u8; 12],
+        ciphertext: &'a [u8],
+    },
+    /// The server's answer to a `Query`, encrypted under the session's reply key.
+    Reply {
+        nonce: &'a [u8; 12],
+        ciphertext: &'a [u8],
+    },
 }
 
 impl<'a> Packet<'a> {
@@ -47,6 +57,22 @@ impl<'a> Packet<'a> {
                 let ciphertext = &buf[13..];
                 Ok(Packet::AppData { nonce, ciphertext })
             }
+            KIND_QUERY => {
+                if buf.len() < 13 {
+                    bail!("Malformed Query (Header too small)");
+                }
+                let nonce = array_ref_12(buf, 1);
+                let ciphertext = &buf[13..];
+                Ok(Packet::Query { nonce, ciphertext })
+            }
+            KIND_REPLY => {
+                if buf.len() < 13 {
+                    bail!("Malformed Reply (Header too small)");
+                }
+                let nonce = array_ref_12(buf, 1);
+                let ciphertext = &buf[13..];
+                Ok(Packet::Reply { nonce, ciphertext })
+            }
             _ => bail!("Unknown packet kind: {}", buf[0]),
         }
     }
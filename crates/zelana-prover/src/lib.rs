@@ -0,0 +1,41 @@
+pub mod native;
+#[cfg(feature = "sp1")]
+pub mod sp1;
+
+pub use native::NativeProver;
+#[cfg(feature = "sp1")]
+pub use sp1::Sp1Prover;
+use {
+    anyhow::Result,
+    zelana_core::{AggregatePublicValues, BatchInput, BatchPublicValues},
+};
+
+/// A proven batch.
+#[derive(Debug, Clone)]
+pub struct BatchProof {
+    /// What the proof commits to, decoded.
+    pub public_values: BatchPublicValues,
+    /// The backend's serialized proof. Empty for `NativeProver`.
+    pub proof: Vec<u8>,
+}
+
+/// A proof of a run of consecutive batches.
+#[derive(Debug, Clone)]
+pub struct AggregateProof {
+    /// What the proof commits to, decoded.
+    pub public_values: AggregatePublicValues,
+    /// The backend's serialized proof. Empty for `NativeProver`.
+    pub proof: Vec<u8>,
+}
+
+/// Turns a recorded batch into a proof of its state transition.
+///
+/// Backends run the same Guest logic (`zelana_execution::execute_batch`), so they
+/// agree on the public values and differ only in what backs them.
+pub trait Prover: Send + Sync {
+    /// Proves `input`. Fails if the Guest would reject the batch.
+    fn prove(&self, input: &BatchInput) -> Result<BatchProof>;
+
+    /// Folds the proofs of consecutive batches, in batch order, into one proof.
+    fn aggregate(&self, proofs: &[BatchProof]) -> Result<AggregateProof>;
+}
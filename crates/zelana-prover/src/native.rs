@@ -0,0 +1,101 @@
+use {
+    crate::{AggregateProof, BatchProof, Prover},
+    anyhow::Result,
+    zelana_core::{AggregatePublicValues, BatchInput, BatchPublicValues},
+    zelana_execution::execute_batch,
+};
+
+/// Runs the Guest logic directly on the host, without the zkVM.
+///
+/// It produces the same public values as the SP1 backend, but its proofs are empty
+/// and prove nothing. Meant for tests and local development only.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NativeProver;
+
+impl NativeProver {
+    /// Stands in for the batch program's verifying key in aggregate public values.
+    pub const BATCH_PROGRAM: [u32; 8] = [0u32; 8];
+}
+
+impl Prover for NativeProver {
+    fn prove(&self, input: &BatchInput) -> Result<BatchProof> {
+        let output = execute_batch(input.clone())?;
+        Ok(BatchProof {
+            public_values: output.public_values,
+            proof: Vec::new(),
+        })
+    }
+
+    fn aggregate(&self, proofs: &[BatchProof]) -> Result<AggregateProof> {
+        let batches: Vec<BatchPublicValues> = proofs
+            .iter()
+            .map(|proof| proof.public_values.clone())
+            .collect();
+        Ok(AggregateProof {
+            public_values: AggregatePublicValues::new(Self::BATCH_PROGRAM, &batches)?,
+            proof: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        ed25519_dalek::SigningKey,
+        std::collections::HashMap,
+        zelana_core::{
+            identity::AccountId, test_utils::signed_transfer, AccountData, L2Transaction,
+        },
+        zelana_execution::{ProvableStore, RecordingStore, ZkMemStore},
+    };
+
+    #[test]
+    fn test_native_prover_commits_recorded_batch() {
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let alice = AccountId(key.verifying_key().to_bytes());
+        let bob = AccountId([2u8; 32]);
+
+        let mut state = HashMap::new();
+        state.insert(
+            alice,
+            AccountData {
+                balance: 100,
+                nonce: 0,
+            },
+        );
+
+        let mut recorder = RecordingStore::new(ZkMemStore::new(state), 4, 1, None).unwrap();
+        recorder.execute(signed_transfer(&key, bob, 30, 0)).unwrap();
+        recorder
+            .execute(signed_transfer(&key, bob, 500, 1))
+            .unwrap();
+        let (input, store) = recorder.finish().unwrap();
+
+        let proof = NativeProver.prove(&input).unwrap();
+        assert!(proof.proof.is_empty());
+        assert_eq!(proof.public_values.batch_height, 4);
+        assert_eq!(proof.public_values.pre_state_root, input.pre_state_root);
+        assert_eq!(
+            proof.public_values.post_state_root,
+            store.state_root().unwrap()
+        );
+
+        // A batch the Guest would reject can't be proven natively either
+        let mut forged = input.clone();
+        if let L2Transaction::Transfer(tx) = &mut forged.transactions[0] {
+            tx.data.amount += 1;
+        }
+        assert!(NativeProver.prove(&forged).is_err());
+
+        // A single batch aggregates to its own roots
+        let aggregate = NativeProver.aggregate(&[proof.clone()]).unwrap();
+        assert_eq!(aggregate.public_values.first_batch_height, 4);
+        assert_eq!(
+            aggregate.public_values.post_state_root,
+            proof.public_values.post_state_root
+        );
+        // Proofs that don't chain can't be aggregated
+        assert!(NativeProver.aggregate(&[proof.clone(), proof]).is_err());
+    }
+}
@@ -0,0 +1,131 @@
+use {
+    crate::{AggregateProof, BatchProof, Prover},
+    anyhow::{anyhow, bail, Context, Result},
+    sp1_sdk::{
+        EnvProver, HashableKey, ProverClient, SP1Proof, SP1ProofMode, SP1ProofWithPublicValues,
+        SP1ProvingKey, SP1Stdin, SP1VerifyingKey,
+    },
+    zelana_core::{AggregateInput, AggregatePublicValues, BatchInput, BatchPublicValues},
+};
+
+/// Proves batches by running the Guest ELF in the SP1 zkVM.
+///
+/// The proving mode (CPU, CUDA, network) comes from the `SP1_PROVER` environment variable.
+pub struct Sp1Prover {
+    client: EnvProver,
+    elf: Vec<u8>,
+    pk: SP1ProvingKey,
+    vk: SP1VerifyingKey,
+    mode: SP1ProofMode,
+    aggregator: Option<SP1ProvingKey>,
+}
+
+impl Sp1Prover {
+    /// Sets up the proving key for the compiled Guest `elf`. Batches get core proofs.
+    pub fn new(elf: &[u8]) -> Self {
+        let client = ProverClient::from_env();
+        let (pk, vk) = client.setup(elf);
+        Self {
+            client,
+            elf: elf.to_vec(),
+            pk,
+            vk,
+            mode: SP1ProofMode::Core,
+            aggregator: None,
+        }
+    }
+
+    /// Proves batches as compressed proofs, the only kind `aggregate` can verify.
+    pub fn compressed(mut self) -> Self {
+        self.mode = SP1ProofMode::Compressed;
+        self
+    }
+
+    /// Enables `aggregate` with the compiled aggregation Guest `elf`.
+    pub fn with_aggregator(mut self, elf: &[u8]) -> Self {
+        let (pk, _vk) = self.client.setup(elf);
+        self.aggregator = Some(pk);
+        self
+    }
+
+    fn stdin<T>(input: &T) -> Result<SP1Stdin>
+    where
+        T: wincode::SchemaWrite<Src = T>,
+    {
+        let bytes =
+            wincode::serialize(input).map_err(|e| anyhow!("Failed to serialize input: {}", e))?;
+        let mut stdin = SP1Stdin::new();
+        stdin.write(&bytes);
+        Ok(stdin)
+    }
+
+    /// Executes the Guest without proving (fast mode) and returns its cycle count.
+    pub fn simulate(&self, input: &BatchInput) -> Result<u64> {
+        let (_, report) = self
+            .client
+            .execute(&self.elf, &Self::stdin(input)?)
+            .run()
+            .context("Execution failed inside ZKVM")?;
+        Ok(report.total_instruction_count())
+    }
+
+    /// Proves `input` and returns the raw SP1 proof with its committed public values.
+    pub fn prove_with_io(&self, input: &BatchInput) -> Result<SP1ProofWithPublicValues> {
+        self.client
+            .prove(&self.pk, &Self::stdin(input)?)
+            .mode(self.mode)
+            .run()
+            .context("Proving failed")
+    }
+}
+
+impl Prover for Sp1Prover {
+    fn prove(&self, input: &BatchInput) -> Result<BatchProof> {
+        let proof = self.prove_with_io(input)?;
+        let public_values = BatchPublicValues::from_bytes(proof.public_values.as_slice())?;
+        Ok(BatchProof {
+            public_values,
+            proof: bincode::serialize(&proof)?,
+        })
+    }
+
+    fn aggregate(&self, proofs: &[BatchProof]) -> Result<AggregateProof> {
+        let pk = self
+            .aggregator
+            .as_ref()
+            .context("No aggregation program configured")?;
+
+        let mut input = AggregateInput {
+            batch_program: self.vk.hash_u32(),
+            batches: Vec::with_capacity(proofs.len()),
+        };
+        let mut reduced = Vec::with_capacity(proofs.len());
+        for proof in proofs {
+            let proof: SP1ProofWithPublicValues = bincode::deserialize(&proof.proof)?;
+            let SP1Proof::Compressed(inner) = proof.proof else {
+                bail!("Only compressed batch proofs can be aggregated");
+            };
+            input.batches.push(proof.public_values.to_vec());
+            reduced.push(inner);
+        }
+
+        // The Guest reads the input first, then verifies one proof per batch
+        let mut stdin = Self::stdin(&input)?;
+        for inner in reduced {
+            stdin.write_proof(*inner, self.vk.vk.clone());
+        }
+
+        // Groth16 is what the L1 verifier checks
+        let proof = self
+            .client
+            .prove(pk, &stdin)
+            .groth16()
+            .run()
+            .context("Aggregation failed")?;
+        let public_values = AggregatePublicValues::from_bytes(proof.public_values.as_slice())?;
+        Ok(AggregateProof {
+            public_values,
+            proof: bincode::serialize(&proof)?,
+        })
+    }
+}
@@ -117,7 +117,7 @@ async fn main() -> anyhow::Result<()> {
     };
 
     // Sign with the L1 Key (Ed25519)
-    let msg = wincode::serialize(&tx_data)?;
+    let msg = tx_data.signing_message();
     let signing_key = SigningKey::from_bytes(&user.secret_bytes()[0..32].try_into().unwrap());
     let signature = signing_key.sign(&msg).to_bytes().to_vec();
 
@@ -125,6 +125,8 @@ async fn main() -> anyhow::Result<()> {
         data: tx_data,
         signature,
         signer_pubkey: user.pubkey().to_bytes(),
+        // The L2 account is the L1 key itself, so there is no privacy key
+        privacy_pubkey: None,
     };
 
     client.send_transaction(signed_tx).await?;
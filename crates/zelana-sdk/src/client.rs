@@ -4,9 +4,13 @@ use {
         net::UdpSocket,
         time::{timeout, Duration},
     },
-    zelana_core::{L2Transaction, SignedTransaction},
+    zelana_core::{
+        AccountId, L2Transaction, PendingDeposit, Query, QueryReply, SignedTransaction,
+        WithdrawRequest,
+    },
     zelana_net::{
         protocol::Packet, EphemeralKeyPair, SessionKeys, KIND_APP_DATA, KIND_CLIENT_HELLO,
+        KIND_QUERY,
     },
 };
 
@@ -68,13 +72,54 @@ impl ZelanaClient {
     }
 
     /// Encrypts and sends a signed transaction.
-    /// This is a "Fire and Forget" operation over UDP.
+    /// This is a "Fire and Forget" operation over UDP, so transactions the Sequencer
+    /// would reject for their signature or sender are refused here first.
     pub async fn send_transaction(&mut self, tx: SignedTransaction) -> Result<()> {
+        tx.verify().context("Transaction is not authorized")?;
+
         // 1. Wrap in L2Transaction enum
-        let l2_tx = L2Transaction::Transfer(tx);
+        self.send(&L2Transaction::Transfer(tx)).await
+    }
+
+    /// Encrypts and sends a signed withdrawal to L1.
+    /// Once its batch is sealed, the withdrawal is queued for the L1 bridge to release.
+    pub async fn send_withdrawal(&mut self, req: WithdrawRequest) -> Result<()> {
+        req.verify().context("Withdrawal is not authorized")?;
+
+        self.send(&L2Transaction::Withdraw(req)).await
+    }
+
+    /// Asks the Sequencer for deposits to `account` it has seen on L1 but not credited
+    /// yet, oldest first. Only the first few are listed.
+    ///
+    /// These are not final: if L1 reorgs them out, they never get credited.
+    pub async fn pending_deposits(&mut self, account: AccountId) -> Result<Vec<PendingDeposit>> {
+        let plaintext = wincode::serialize(&Query::PendingDeposits { account })
+            .context("Serialization failed")?;
+        let payload = self.session.encrypt(&plaintext)?;
+
+        let mut frame = Vec::with_capacity(1 + payload.len());
+        frame.push(KIND_QUERY);
+        frame.extend_from_slice(&payload);
+        self.socket.send(&frame).await?;
+
+        let mut buf = vec![0u8; 1500];
+        let len = timeout(Duration::from_secs(2), self.socket.recv(&mut buf))
+            .await
+            .map_err(|_| anyhow!("Query timed out"))??;
+
+        let reply = match Packet::parse(&buf[..len])? {
+            Packet::Reply { nonce, ciphertext } => self.session.decrypt_reply(nonce, ciphertext)?,
+            _ => return Err(anyhow!("Expected Reply, got something else")),
+        };
+        match wincode::deserialize(&reply).context("Malformed Reply")? {
+            QueryReply::PendingDeposits(deposits) => Ok(deposits),
+        }
+    }
 
+    async fn send(&mut self, l2_tx: &L2Transaction) -> Result<()> {
         // 2. Serialize
-        let plaintext = wincode::serialize(&l2_tx).context("Serialization failed")?;
+        let plaintext = wincode::serialize(l2_tx).context("Serialization failed")?;
 
         // 3. Encrypt (adds Nonce automatically)
         let payload = self.session.encrypt(&plaintext)?;
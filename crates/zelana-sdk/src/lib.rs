@@ -4,5 +4,9 @@ pub mod wallet;
 pub use {
     client::ZelanaClient,
     wallet::ZelanaWallet,
-    zelana_core::{AccountId, L2Transaction, SignedTransaction, TransactionData},
+    zelana_core::{
+        AccountId, L2Transaction, PendingDeposit, SignedTransaction, TransactionData, WithdrawData,
+        WithdrawRequest,
+    },
+    zelana_execution::{ExecutionError, TxEvent, TxReceipt, TxStatus},
 };
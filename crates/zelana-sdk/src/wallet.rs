@@ -4,7 +4,7 @@ use {
     x25519_dalek::{PublicKey as X25519PublicKey, StaticSecret},
     zelana_core::{
         identity::{AccountId, IdentityKeys},
-        transaction::{SignedTransaction, TransactionData},
+        transaction::{SignedTransaction, TransactionData, WithdrawData, WithdrawRequest},
     },
 };
 
@@ -55,15 +55,34 @@ impl ZelanaWallet {
     /// Signs a transaction payload.
     /// This automatically attaches the signer's public key for the ZK Circuit.
     pub fn sign_transaction(&self, data: TransactionData) -> SignedTransaction {
-        let msg = wincode::serialize(&data).expect("Serialization failed");
-
-        // Sign the serialized bytes
-        let signature = self.signing_key.sign(&msg).to_bytes().to_vec();
+        // Sign the domain-tagged serialized bytes
+        let signature = self
+            .signing_key
+            .sign(&data.signing_message())
+            .to_bytes()
+            .to_vec();
 
         SignedTransaction {
             data,
             signature,
             signer_pubkey: self.signing_key.verifying_key().to_bytes(),
+            privacy_pubkey: Some(X25519PublicKey::from(&self.privacy_key).to_bytes()),
+        }
+    }
+
+    /// Signs a withdrawal to L1 the same way as `sign_transaction`.
+    pub fn sign_withdrawal(&self, data: WithdrawData) -> WithdrawRequest {
+        let signature = self
+            .signing_key
+            .sign(&data.signing_message())
+            .to_bytes()
+            .to_vec();
+
+        WithdrawRequest {
+            data,
+            signature,
+            signer_pubkey: self.signing_key.verifying_key().to_bytes(),
+            privacy_pubkey: Some(X25519PublicKey::from(&self.privacy_key).to_bytes()),
         }
     }
 }
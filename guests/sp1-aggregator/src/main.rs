@@ -0,0 +1,36 @@
+#![no_main]
+sp1_zkvm::entrypoint!(main);
+
+use {
+    sha2::{Digest, Sha256},
+    zelana_core::{AggregateInput, AggregatePublicValues, BatchPublicValues},
+};
+
+pub fn main() {
+    //Read Input (the public values of each batch, in order)
+    let raw: Vec<u8> = sp1_zkvm::io::read();
+    let input: AggregateInput =
+        wincode::deserialize(&raw).expect("failed to deserialize aggregateinput");
+
+    //Verify a proof of the batch program for each batch
+    //The proofs themselves were written to stdin by the host; SP1 checks them after execution.
+    let mut batches = Vec::with_capacity(input.batches.len());
+    for bytes in &input.batches {
+        let digest: [u8; 32] = Sha256::digest(bytes).into();
+        sp1_zkvm::lib::verify::verify_sp1_proof(&input.batch_program, &digest);
+
+        let values = BatchPublicValues::from_bytes(bytes)
+            .unwrap_or_else(|e| panic!("Bad batch public values: {}", e));
+        batches.push(values);
+    }
+
+    //Chain the batches: same chain, consecutive heights, each starting where the last ended
+    let values = match AggregatePublicValues::new(input.batch_program, &batches) {
+        Ok(values) => values,
+        Err(e) => panic!("Invalid aggregation: {}", e),
+    };
+
+    //Commit the Result
+    //The proof now publicly asserts: "Batches A..=B of this chain take Pre-Root X to Post-Root Y"
+    sp1_zkvm::io::commit_slice(&values.to_bytes());
+}
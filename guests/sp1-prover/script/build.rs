@@ -2,4 +2,5 @@ use sp1_build::build_program;
 
 fn main() {
     build_program("../");
+    build_program("../../sp1-aggregator");
 }
@@ -3,10 +3,10 @@ use {
     std::{collections::HashMap, fs::File, io::Write},
     x25519_dalek::{PublicKey as XPub, StaticSecret},
     zelana_core::{
-        prover::{AccountData, BatchInput},
-        IdentityKeys, L2Transaction, SignedTransaction, TransactionData,
+        crypto::hash_bytes, prover::AccountData, AccountId, IdentityKeys, L2Transaction,
+        SignedTransaction, TransactionData,
     },
-    zelana_execution::ZkMemStore,
+    zelana_execution::{RecordingStore, ZkMemStore},
 };
 
 fn main() -> anyhow::Result<()> {
@@ -21,18 +21,27 @@ fn main() -> anyhow::Result<()> {
     };
     let my_id = keys.derive_id();
 
-    // 2. Create Initial State (Witness)
-    let mut witness = HashMap::new();
-    witness.insert(
+    // 2. Create Initial State
+    // Our account plus unrelated accounts the batch never touches.
+    let mut state = HashMap::new();
+    state.insert(
         my_id,
         AccountData {
             balance: 1000,
             nonce: 0,
         },
     );
+    for i in 0..100u8 {
+        state.insert(
+            AccountId(hash_bytes(&[i])),
+            AccountData {
+                balance: 1 + i as u64,
+                nonce: 0,
+            },
+        );
+    }
 
-    // We clone the witness map because ZkMemStore consumes it (or you can clone inside new)
-    let store = ZkMemStore::new(witness.clone());
+    let store = ZkMemStore::new(state);
     let pre_root = store.compute_root();
     println!("Calculated Pre-Root: {:?}", pre_root);
 
@@ -53,24 +62,20 @@ fn main() -> anyhow::Result<()> {
         data: tx_data,
         signature,
         signer_pubkey: keys.signer_pk,
+        privacy_pubkey: Some(keys.privacy_pk),
     };
 
-    // 4. Calculate Pre-State Root (Must match what the guest calculates!)
-    // We use the same ZkMemStore logic locally to get the expected root
-    // (In production, the Sequencer provides this).
-    // For this test script, we need to replicate the sorting/hashing logic of ZkMemStore
-    // OR just import it if we moved ZkMemStore to a shared lib.
-    // For now, let's trust the guest will compute it from the witness map.
-
-    // HACK: To make the Guest pass the "Pre-Root Check", we need to know what the Root IS.
-    // The easiest way is to let the Guest run once, panic with "Expected X got Y", and copy Y.
-    // OR, we move `simple_store` to `zelana-execution` so we can use it here.
-
-    let input = BatchInput {
-        pre_state_root: pre_root,
-        transactions: vec![L2Transaction::Transfer(signed)],
-        witness_accounts: witness,
-    };
+    // 4. Execute and Record
+    // The recorder collects the touched accounts and proves them against the Pre-State Root.
+    let mut recorder = RecordingStore::new(store, 1, 1, None)?;
+    recorder.execute(L2Transaction::Transfer(signed))?;
+    let (input, store) = recorder.finish()?;
+    println!(
+        "Witness: {} accounts, {} proof nodes",
+        input.witness_accounts.len(),
+        input.witness_proof.nodes.len()
+    );
+    println!("Expected Post-Root: {:?}", store.compute_root());
 
     // 5. Save
     let bytes = wincode::serialize(&input)
@@ -79,6 +84,6 @@ fn main() -> anyhow::Result<()> {
     let mut file = File::create("batch.bin")?;
     file.write_all(&bytes)?;
 
-    println!("Generated batch.json with 1 tx. (Pre-Root is invalid, expect panic on first run!)");
+    println!("Generated batch.bin with {} tx.", input.transactions.len());
     Ok(())
 }
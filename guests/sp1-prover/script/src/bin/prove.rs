@@ -1,12 +1,13 @@
 use {
     anyhow::Context,
     clap::Parser,
-    sp1_sdk::{ProverClient, SP1Stdin},
+    sp1_sdk::SP1ProofWithPublicValues,
     std::{
         fs::File,
         io::{BufReader, Read},
     },
-    zelana_core::prover::BatchInput,
+    zelana_core::prover::{BatchInput, BatchPublicValues},
+    zelana_prover::Sp1Prover,
 };
 
 #[derive(Parser, Debug)]
@@ -40,30 +41,53 @@ async fn main() -> anyhow::Result<()> {
 
     println!("Batch contains {} transactions.", batch.transactions.len());
 
-    let mut stdin = SP1Stdin::new();
-    stdin.write(&bytes);
-
     //Initialize Prover
-    let client = ProverClient::new();
-    let (pk, vk) = client.setup(GUEST_ELF);
+    let prover = Sp1Prover::new(GUEST_ELF);
 
     //Execute (Fast Mode - No Proof) first to check logic
     println!("Simulating execution...");
-    let (_, report) = client
-        .execute(GUEST_ELF, &stdin.clone())
-        .run()
-        .expect("Execution failed inside ZKVM. Check your Guest logic!");
+    let cycles = prover.simulate(&batch).context("Check your Guest logic!")?;
 
-    println!(
-        "Simulation Successful! Cycles: {}",
-        report.total_instruction_count()
-    );
+    println!("Simulation Successful! Cycles: {}", cycles);
 
     println!("Generating Proof...");
-    let proof = client.prove(&pk, &stdin).run().expect("Proving failed");
+    let proof = prover.prove_with_io(&batch)?;
 
     println!("Proof Generated!");
     proof.save("proof-with-io.bin")?;
 
+    //Decode what the proof commits to, as an L1 verifier would
+    let saved = SP1ProofWithPublicValues::load("proof-with-io.bin")?;
+    let values = BatchPublicValues::from_bytes(saved.public_values.as_slice())?;
+    anyhow::ensure!(
+        values.pre_state_root == batch.pre_state_root,
+        "Proof is for a different pre-state root"
+    );
+    println!("Public Values (v{}):", values.version);
+    println!(
+        "  Batch:            {} (chain {})",
+        values.batch_height, values.chain_id
+    );
+    println!("  Pre-State Root:   {}", hex::encode(values.pre_state_root));
+    println!(
+        "  Post-State Root:  {}",
+        hex::encode(values.post_state_root)
+    );
+    println!(
+        "  Transactions:     {}",
+        hex::encode(values.transactions_root)
+    );
+    match values.deposits {
+        Some(range) => println!(
+            "  Deposits:         l1_seq {}..={}",
+            range.first_l1_seq, range.last_l1_seq
+        ),
+        None => println!("  Deposits:         none"),
+    }
+    println!(
+        "  Withdrawals Root: {}",
+        hex::encode(values.withdrawals_root)
+    );
+
     Ok(())
 }
@@ -1,12 +1,7 @@
 #![no_main]
 sp1_zkvm::entrypoint!(main);
 
-use {
-    zelana_core::prover::BatchInput,
-    zelana_execution::{BatchExecutor, ZkMemStore},
-};
-
-mod simple_store;
+use {zelana_core::prover::BatchInput, zelana_execution::execute_batch};
 
 pub fn main() {
     //Read Input (The batch from the Sequencer)
@@ -14,34 +9,15 @@ pub fn main() {
     let raw: Vec<u8> = sp1_zkvm::io::read();
     let input: BatchInput = wincode::deserialize(&raw).expect("failed to deserialize batchinput");
 
-    //rebuild the state map from the witness data provided
-    let mut store = ZkMemStore::new(input.witness_accounts);
-
-    //Verify Pre-State Root
-    //"Does the witness data match the Root we claimed we started with?"
-    let calculated_pre_root = store.compute_root();
-
-    if calculated_pre_root != input.pre_state_root {
-        println!("Guest Calc Root: {:?}", calculated_pre_root);
-        println!("Input Pre Root:  {:?}", input.pre_state_root);
-        panic!("Fraud Detected: Witness data does not match Pre-State Root!");
-    }
-    //Execution Loop
-    //We run the EXACT SAME logic as the Sequencer
-    let mut executor = BatchExecutor::new(&mut store);
-
-    for tx in input.transactions {
-        // panic if execution fails. In a ZK Rollup, a "Batch" must contain
-        // only valid transactions. Invalid ones should be dropped by Sequencer.
-        executor
-            .execute(&tx)
-            .expect("Transaction Execution Failed inside ZK");
-    }
-
-    //Compute Post-State Root
-    let new_root = store.compute_root();
+    //Verify and re-execute the batch with the same code the Sequencer and the host tests run.
+    //Ed25519 runs on SP1's curve25519 precompile (see the workspace `[patch.crates-io]`).
+    let output = match execute_batch(input) {
+        Ok(output) => output,
+        Err(e) => panic!("Fraud Detected: {}", e),
+    };
 
     //Commit the Result
-    //The proof now publicly asserts: "Given Pre-Root X, running these Txs results in Post-Root Y"
-    sp1_zkvm::io::commit(&new_root);
+    //The proof now publicly asserts: "Given Pre-Root X, running these Txs (with these deposits)
+    //results in Post-Root Y and releases the withdrawals under Withdrawals-Root W"
+    sp1_zkvm::io::commit_slice(&output.public_values.to_bytes());
 }
@@ -0,0 +1,562 @@
+use {
+    crate::db::RocksDbStore,
+    anyhow::{Context, Result},
+    std::{
+        env,
+        time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+    },
+    wincode::{SchemaRead, SchemaWrite},
+    zelana_core::{
+        BatchHeader, BatchInput, BatchPublicValues, DepositEvent, ExitProof, L2Transaction,
+        WithdrawData,
+    },
+    zelana_execution::{
+        executed_withdrawals, receipts_root, ExecutionError, RecordingStore, TxReceipt,
+    },
+};
+
+/// When to seal the open batch.
+#[derive(Debug, Clone)]
+pub struct BatchConfig {
+    /// Seal once the batch holds this many transactions.
+    pub max_txs: usize,
+    /// Seal once the batch has been open this long.
+    pub max_age: Duration,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            max_txs: 100,
+            max_age: Duration::from_secs(10),
+        }
+    }
+}
+
+impl BatchConfig {
+    /// Reads `BATCH_MAX_TXS` and `BATCH_MAX_AGE_MS`, falling back to the defaults.
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        Self {
+            max_txs: env::var("BATCH_MAX_TXS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.max_txs),
+            max_age: env::var("BATCH_MAX_AGE_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .map(Duration::from_millis)
+                .unwrap_or(default.max_age),
+        }
+    }
+}
+
+/// A sealed batch as persisted in the `batches` column family.
+#[derive(Debug, Clone, SchemaRead, SchemaWrite)]
+pub struct SealedBatch {
+    /// Height, parent hash and pre/post state roots of the batch.
+    pub header: BatchHeader,
+    /// Exactly what the SP1 Guest needs to re-execute the batch.
+    pub input: BatchInput,
+    /// One receipt per transaction, in batch order.
+    pub receipts: Vec<TxReceipt>,
+}
+
+impl SealedBatch {
+    /// The batch's executed withdrawals, in the order of `header.withdrawals_root`.
+    pub fn withdrawals(&self) -> Vec<QueuedWithdrawal> {
+        let mut queued = Vec::new();
+        for (tx, receipt) in self.input.transactions.iter().zip(&self.receipts) {
+            if let L2Transaction::Withdraw(req) = tx {
+                if receipt.is_success() {
+                    queued.push(QueuedWithdrawal {
+                        batch_height: self.header.height,
+                        position: queued.len() as u32,
+                        tx_hash: receipt.tx_hash,
+                        data: req.data.clone(),
+                    });
+                }
+            }
+        }
+        queued
+    }
+
+    /// The batch's deposits, in `l1_seq` order, including ones that failed to credit
+    /// (e.g. on a balance overflow): their `l1_seq` is used up all the same.
+    pub fn deposits(&self) -> Vec<ProcessedDeposit> {
+        self.input
+            .transactions
+            .iter()
+            .zip(&self.receipts)
+            .filter_map(|(tx, receipt)| match tx {
+                L2Transaction::Deposit(event) => Some(ProcessedDeposit {
+                    batch_height: self.header.height,
+                    tx_hash: receipt.tx_hash,
+                    event: event.clone(),
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// An L1 deposit applied on L2. Kept in the `deposits` column family, the ledger of
+/// every processed `l1_seq`.
+#[derive(Debug, Clone, PartialEq, SchemaRead, SchemaWrite)]
+pub struct ProcessedDeposit {
+    pub batch_height: u64,
+    /// `L2Transaction::hash` of the deposit, the key of its receipt.
+    pub tx_hash: [u8; 32],
+    pub event: DepositEvent,
+}
+
+/// A withdrawal burned on L2 that the L1 bridge still has to pay out.
+/// Kept in the `withdrawals` column family, ordered by batch and position.
+#[derive(Debug, Clone, PartialEq, SchemaRead, SchemaWrite)]
+pub struct QueuedWithdrawal {
+    pub batch_height: u64,
+    /// Index of the withdrawal's leaf (`WithdrawData::leaf_hash`) in the batch's
+    /// `withdrawals_root`.
+    pub position: u32,
+    /// `L2Transaction::hash` of the `WithdrawRequest`.
+    pub tx_hash: [u8; 32],
+    pub data: WithdrawData,
+}
+
+struct OpenBatch {
+    store: RecordingStore<RocksDbStore>,
+    opened_at: Instant,
+}
+
+/// Groups executed transactions into numbered batches.
+///
+/// Transactions run against the open batch's `RecordingStore`, so their writes stay
+/// in memory and the database keeps the pre-state until the batch is sealed. Sealing
+/// writes the state changes, the batch, its withdrawal queue and deposit ledger entries,
+/// the new height and the last applied `l1_seq` in one atomic write.
+pub struct BatchBuilder {
+    db: RocksDbStore,
+    chain_id: u64,
+    config: BatchConfig,
+    open: Option<OpenBatch>,
+}
+
+impl BatchBuilder {
+    pub fn new(db: RocksDbStore, chain_id: u64, config: BatchConfig) -> Self {
+        Self {
+            db,
+            chain_id,
+            config,
+            open: None,
+        }
+    }
+
+    /// Executes `tx` in the open batch, opening one if needed.
+    /// Also returns the sealed batch if this transaction filled it.
+    pub fn execute(
+        &mut self,
+        tx: L2Transaction,
+    ) -> Result<(TxReceipt, Option<SealedBatch>), ExecutionError> {
+        let mut open = match self.open.take() {
+            Some(open) => open,
+            None => OpenBatch {
+                store: RecordingStore::new(
+                    self.db.clone(),
+                    self.db.batch_height()? + 1,
+                    self.chain_id,
+                    self.db.l1_seq()?,
+                )?,
+                opened_at: Instant::now(),
+            },
+        };
+
+        let result = open.store.execute(tx);
+        let full = open.store.transactions().len() >= self.config.max_txs;
+        self.open = Some(open);
+        let receipt = result?;
+
+        let sealed = if full { self.seal()? } else { None };
+        Ok((receipt, sealed))
+    }
+
+    /// Seals the open batch if it has reached `max_age`.
+    pub fn seal_if_due(&mut self) -> Result<Option<SealedBatch>> {
+        match &self.open {
+            Some(open) if open.opened_at.elapsed() >= self.config.max_age => self.seal(),
+            _ => Ok(None),
+        }
+    }
+
+    /// Seals and persists the open batch. Batches without transactions are dropped.
+    /// If sealing fails, the batch stays open with everything executed in it.
+    pub fn seal(&mut self) -> Result<Option<SealedBatch>> {
+        let Some(open) = &self.open else {
+            return Ok(None);
+        };
+        if open.store.transactions().is_empty() {
+            self.open = None;
+            return Ok(None);
+        }
+
+        let input = open.store.batch_input()?;
+        let receipts = open.store.receipts().to_vec();
+        let writes = open.store.writes();
+
+        let height = input.batch_height;
+        let parent_hash = match self.db.get_batch(height - 1)? {
+            Some(parent) => parent.header.hash(),
+            None => BatchHeader::GENESIS_PARENT,
+        };
+
+        let mut write = self.db.write();
+        for (id, state) in writes {
+            write.set_account(id, state)?;
+        }
+
+        // The same public values the Guest will commit for this batch
+        let values = BatchPublicValues::new(
+            &input,
+            write.state_root()?,
+            &executed_withdrawals(&input.transactions, &receipts),
+        );
+        let header = BatchHeader::new(
+            parent_hash,
+            &input.transactions,
+            &values,
+            receipts_root(&receipts),
+            SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+        );
+        // The L1 cursor only moves once the deposits are persisted with their batch
+        if let Some(deposits) = values.deposits {
+            write.set_l1_seq(deposits.last_l1_seq)?;
+        }
+        for receipt in &receipts {
+            write.put_receipt(receipt)?;
+        }
+        let batch = SealedBatch {
+            header,
+            input,
+            receipts,
+        };
+        for withdrawal in batch.withdrawals() {
+            write.put_withdrawal(&withdrawal)?;
+        }
+        for deposit in batch.deposits() {
+            write.put_deposit(&deposit)?;
+        }
+        write.put_batch(&batch)?;
+        write.set_batch_height(height)?;
+        write.commit()?;
+
+        self.open = None;
+        Ok(Some(batch))
+    }
+}
+
+/// Writes the `BatchInput` of a sealed batch to `path` in the format `prove` reads.
+pub fn export_batch(db: &RocksDbStore, height: u64, path: &str) -> Result<()> {
+    let batch = db
+        .get_batch(height)?
+        .with_context(|| format!("Batch {} not found", height))?;
+
+    let bytes = wincode::serialize(&batch.input)?;
+    std::fs::write(path, bytes)?;
+    Ok(())
+}
+
+/// The proof a user submits to the L1 bridge to claim the withdrawal at `position`
+/// of batch `height`.
+pub fn exit_proof(db: &RocksDbStore, height: u64, position: u32) -> Result<ExitProof> {
+    let batch = db
+        .get_batch(height)?
+        .with_context(|| format!("Batch {} not found", height))?;
+
+    let withdrawals: Vec<WithdrawData> = batch.withdrawals().into_iter().map(|w| w.data).collect();
+    ExitProof::new(height, &withdrawals, position as usize)
+        .with_context(|| format!("Batch {} has no withdrawal {}", height, position))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        zelana_core::{
+            prover::DepositRange,
+            test_utils::{temp_path, unsigned_transfer},
+            AccountId, DepositEvent, WithdrawRequest,
+        },
+        zelana_execution::{
+            execute_batch, AccountState, BatchExecutor, StateStore, TxStatus, WitnessStore,
+        },
+    };
+
+    #[test]
+    fn test_batches_seal_on_size_and_replay_on_witness() {
+        let path = temp_path("batches");
+
+        let alice = AccountId([1u8; 32]);
+        let bob = AccountId([2u8; 32]);
+        let mut db = RocksDbStore::open(&path).unwrap();
+        db.set_account(
+            alice,
+            AccountState {
+                balance: 100,
+                nonce: 0,
+            },
+        )
+        .unwrap();
+        let genesis_root = db.state_root().unwrap();
+
+        let config = BatchConfig {
+            max_txs: 2,
+            max_age: Duration::from_secs(3600),
+        };
+        let mut builder = BatchBuilder::new(db.clone(), 1, config);
+
+        let (receipt, sealed) = builder
+            .execute(unsigned_transfer(alice, bob, 10, 0))
+            .unwrap();
+        assert!(receipt.is_success());
+        assert!(sealed.is_none());
+        // Unsealed writes are not persisted yet
+        assert_eq!(db.get_account(&bob).unwrap().balance, 0);
+
+        let first = builder
+            .execute(unsigned_transfer(alice, bob, 10, 1))
+            .unwrap()
+            .1
+            .unwrap();
+        assert_eq!(first.header.height, 1);
+        assert_eq!(first.header.parent_hash, BatchHeader::GENESIS_PARENT);
+        assert_eq!(first.input.pre_state_root, genesis_root);
+        assert_eq!(first.header.post_state_root, db.state_root().unwrap());
+        assert_eq!(db.get_account(&bob).unwrap().balance, 20);
+        let receipt = db
+            .get_receipt(&first.input.transactions[1].hash())
+            .unwrap()
+            .unwrap();
+        assert_eq!(receipt.index, 1);
+        assert_eq!(first.header.receipts_root, receipts_root(&first.receipts));
+
+        builder
+            .execute(unsigned_transfer(bob, alice, 5, 0))
+            .unwrap();
+        let second = builder.seal().unwrap().unwrap();
+        assert!(second.header.extends(&first.header));
+        assert_eq!(second.input.pre_state_root, first.header.post_state_root);
+        assert_eq!(db.batch_height().unwrap(), 2);
+
+        // The persisted input replays on its witness alone to the recorded post-root
+        let stored = db.get_batch(1).unwrap().unwrap();
+        let input = stored.input;
+        let mut witness = WitnessStore::new(
+            input.pre_state_root,
+            input.witness_accounts,
+            &input.witness_proof,
+        )
+        .unwrap();
+        let mut executor = BatchExecutor::new(&mut witness);
+        for tx in &input.transactions {
+            executor.execute(tx).unwrap();
+        }
+        assert_eq!(witness.compute_root(), stored.header.post_state_root);
+
+        drop(builder);
+        drop(db);
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn test_failed_seal_keeps_the_open_batch() {
+        let path = temp_path("failed-seal");
+
+        let alice = AccountId([1u8; 32]);
+        let bob = AccountId([2u8; 32]);
+        let carol = AccountId([3u8; 32]);
+        let mut db = RocksDbStore::open(&path).unwrap();
+        db.set_account(
+            alice,
+            AccountState {
+                balance: 100,
+                nonce: 0,
+            },
+        )
+        .unwrap();
+
+        let mut builder = BatchBuilder::new(db.clone(), 1, BatchConfig::default());
+        let (receipt, _) = builder
+            .execute(unsigned_transfer(alice, bob, 10, 0))
+            .unwrap();
+        assert!(receipt.is_success());
+
+        // Changing the pre-state under the open batch makes its witness unprovable
+        db.set_account(
+            carol,
+            AccountState {
+                balance: 1,
+                nonce: 0,
+            },
+        )
+        .unwrap();
+        assert!(builder.seal().is_err());
+        assert_eq!(db.batch_height().unwrap(), 0);
+
+        // The acknowledged transfer is still there once sealing can succeed
+        db.set_account(carol, AccountState::default()).unwrap();
+        let sealed = builder.seal().unwrap().unwrap();
+        assert_eq!(sealed.input.transactions.len(), 1);
+        assert_eq!(
+            sealed.input.transactions[0].hash(),
+            unsigned_transfer(alice, bob, 10, 0).hash()
+        );
+        assert_eq!(db.get_account(&bob).unwrap().balance, 10);
+        assert!(builder.seal().unwrap().is_none());
+
+        drop(builder);
+        drop(db);
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    fn withdraw(from: AccountId, amount: u64, nonce: u64) -> L2Transaction {
+        L2Transaction::Withdraw(WithdrawRequest {
+            data: WithdrawData {
+                from,
+                to_l1_address: [7u8; 32],
+                amount,
+                nonce,
+                chain_id: 1,
+            },
+            signature: vec![],
+            signer_pubkey: [0u8; 32],
+            privacy_pubkey: None,
+        })
+    }
+
+    #[test]
+    fn test_sealed_withdrawals_are_queued() {
+        let path = temp_path("withdrawals");
+
+        let alice = AccountId([1u8; 32]);
+        let bob = AccountId([2u8; 32]);
+        let mut db = RocksDbStore::open(&path).unwrap();
+        db.set_account(
+            alice,
+            AccountState {
+                balance: 100,
+                nonce: 0,
+            },
+        )
+        .unwrap();
+
+        let mut builder = BatchBuilder::new(db.clone(), 1, BatchConfig::default());
+        builder.execute(withdraw(alice, 30, 0)).unwrap();
+        // An overdrawn withdrawal is included as failed, but never queued
+        let (failed, _) = builder.execute(withdraw(alice, 1000, 1)).unwrap();
+        assert_eq!(
+            failed.status,
+            TxStatus::Failed(ExecutionError::InsufficientFunds {
+                balance: 70,
+                needed: 1000
+            })
+        );
+        builder
+            .execute(unsigned_transfer(alice, bob, 10, 2))
+            .unwrap();
+        builder.execute(withdraw(alice, 20, 3)).unwrap();
+        let batch = builder.seal().unwrap().unwrap();
+        assert_eq!(batch.input.transactions.len(), 4);
+
+        let queued = db.withdrawals(1).unwrap();
+        assert_eq!(queued, batch.withdrawals());
+        assert_eq!(queued.len(), 2);
+        assert_eq!(queued[1].position, 1);
+        assert_eq!(queued[1].data.amount, 20);
+        assert_eq!(queued[1].data.to_l1_address, [7u8; 32]);
+        assert_eq!(db.get_account(&alice).unwrap().balance, 40);
+
+        // Every queued withdrawal can be claimed against the header's root
+        for w in &queued {
+            let exit = exit_proof(&db, 1, w.position).unwrap();
+            assert_eq!(exit.withdrawal, w.data);
+            assert!(exit.verify(&batch.header.withdrawals_root));
+        }
+        assert!(exit_proof(&db, 1, 2).is_err());
+        assert!(db.withdrawals(2).unwrap().is_empty());
+
+        drop(builder);
+        drop(db);
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    fn deposit(to: AccountId, amount: u64, l1_seq: u64) -> L2Transaction {
+        L2Transaction::Deposit(DepositEvent { to, amount, l1_seq })
+    }
+
+    #[test]
+    fn test_deposits_are_sealed_into_batches() {
+        let path = temp_path("deposits");
+
+        let alice = AccountId([1u8; 32]);
+        let bob = AccountId([2u8; 32]);
+        let db = RocksDbStore::open(&path).unwrap();
+        let mut builder = BatchBuilder::new(db.clone(), 1, BatchConfig::default());
+
+        builder.execute(deposit(alice, 50, 0)).unwrap();
+        builder.execute(deposit(bob, 5, 1)).unwrap();
+        // Like any other transaction, a deposit is only persisted with its batch
+        assert_eq!(db.get_account(&alice).unwrap().balance, 0);
+        assert_eq!(db.l1_seq().unwrap(), None);
+        // A replayed log can't credit twice, even before the seal
+        assert_eq!(
+            builder.execute(deposit(bob, 5, 1)).unwrap_err(),
+            ExecutionError::DepositOutOfOrder {
+                expected: 2,
+                got: 1
+            }
+        );
+
+        let batch = builder.seal().unwrap().unwrap();
+        assert_eq!(batch.input.transactions.len(), 2);
+        assert_eq!(db.get_account(&alice).unwrap().balance, 50);
+        assert_eq!(db.get_account(&bob).unwrap().balance, 5);
+        assert_eq!(db.l1_seq().unwrap(), Some(1));
+        assert_eq!(db.deposits(0).unwrap(), batch.deposits());
+        assert_eq!(db.deposits(1).unwrap()[0].event.to, bob);
+
+        // The Guest proves the deposits together with the rest of the batch
+        let output = execute_batch(batch.input.clone()).unwrap();
+        assert_eq!(
+            output.public_values.deposits,
+            Some(DepositRange {
+                first_l1_seq: 0,
+                last_l1_seq: 1
+            })
+        );
+        assert_eq!(
+            output.public_values.post_state_root,
+            batch.header.post_state_root
+        );
+
+        // The next batch continues from the ledger: duplicates and gaps are rejected
+        assert!(builder.execute(deposit(alice, 1, 0)).is_err());
+        assert_eq!(
+            builder.execute(deposit(alice, 1, 3)).unwrap_err(),
+            ExecutionError::DepositOutOfOrder {
+                expected: 2,
+                got: 3
+            }
+        );
+        builder.execute(deposit(alice, 1, 2)).unwrap();
+        let next = builder.seal().unwrap().unwrap();
+        assert_eq!(next.input.prev_l1_seq, Some(1));
+
+        // Nor does the Guest prove a batch that skips one
+        let mut skipping = next.input.clone();
+        skipping.prev_l1_seq = Some(0);
+        assert!(execute_batch(skipping).is_err());
+
+        drop(builder);
+        drop(db);
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+}
@@ -1,17 +1,54 @@
 use {
+    crate::{
+        batch::{ProcessedDeposit, QueuedWithdrawal, SealedBatch},
+        ingest::IndexerCursor,
+        proving::ProofRecord,
+    },
     anyhow::{Context, Result},
-    rocksdb::{ColumnFamilyDescriptor, Options, DB},
-    std::{path::Path, sync::Arc},
-    zelana_core::AccountId,
-    zelana_execution::{AccountState, StateStore},
+    rocksdb::{
+        ColumnFamily, ColumnFamilyDescriptor, Direction, IteratorMode, Options, WriteBatch, DB,
+    },
+    std::{collections::HashMap, path::Path, sync::Arc},
+    tempfile::TempDir,
+    zelana_core::{
+        merkle::{self, account_leaf, NodeKey, NodeStore, TreeNode},
+        AccountData, AccountId, MultiProof, PendingDeposit,
+    },
+    zelana_execution::{AccountState, ProvableStore, StateStore, TxReceipt},
 };
 
 const CF_ACCOUNTS: &str = "accounts";
+const CF_SMT_NODES: &str = "smt_nodes";
+const CF_META: &str = "meta";
+const CF_RECEIPTS: &str = "receipts";
+const CF_BATCHES: &str = "batches";
+const CF_WITHDRAWALS: &str = "withdrawals";
+const CF_PROOFS: &str = "proofs";
+const CF_DEPOSITS: &str = "deposits";
+const CF_PENDING_DEPOSITS: &str = "pending_deposits";
+
+const COLUMN_FAMILIES: [&str; 9] = [
+    CF_ACCOUNTS,
+    CF_SMT_NODES,
+    CF_META,
+    CF_RECEIPTS,
+    CF_BATCHES,
+    CF_WITHDRAWALS,
+    CF_PROOFS,
+    CF_DEPOSITS,
+    CF_PENDING_DEPOSITS,
+];
+
+const META_BATCH_HEIGHT: &[u8] = b"batch_height";
+const META_L1_SEQ: &[u8] = b"l1_seq";
+const META_INDEXER_CURSOR: &[u8] = b"indexer_cursor";
 
 /// A thread-safe wrapper around RocksDB.
 #[derive(Clone)]
 pub struct RocksDbStore {
     db: Arc<DB>,
+    /// A secondary's own files, removed after the last handle closes the database.
+    _secondary_dir: Option<Arc<TempDir>>,
 }
 
 impl RocksDbStore {
@@ -21,25 +58,184 @@ impl RocksDbStore {
         opts.create_if_missing(true);
         opts.create_missing_column_families(true);
 
-        let cf_opts = Options::default();
-        let families = vec![ColumnFamilyDescriptor::new(CF_ACCOUNTS, cf_opts)];
-
+        let families = COLUMN_FAMILIES
+            .iter()
+            .map(|name| ColumnFamilyDescriptor::new(*name, Options::default()));
         let db = DB::open_cf_descriptors(&opts, path, families)
             .map_err(|e| anyhow::anyhow!("Failed to open RocksDB: {}", e))?;
 
-        Ok(Self { db: Arc::new(db) })
+        let store = Self {
+            db: Arc::new(db),
+            _secondary_dir: None,
+        };
+        store.rebuild_tree_if_missing()?;
+        Ok(store)
+    }
+
+    /// Opens the database at `path` as a read-only secondary, next to the sequencer
+    /// that owns it. Reads see the primary's writes up to the moment of opening.
+    pub fn open_secondary<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut opts = Options::default();
+        // A secondary must keep every table file open
+        opts.set_max_open_files(-1);
+
+        // The secondary's own logs, apart from the primary's files
+        let secondary_dir = tempfile::Builder::new()
+            .prefix("zelana-secondary-")
+            .tempdir()?;
+        let db =
+            DB::open_cf_as_secondary(&opts, path.as_ref(), secondary_dir.path(), COLUMN_FAMILIES)
+                .map_err(|e| anyhow::anyhow!("Failed to open RocksDB as secondary: {}", e))?;
+        Ok(Self {
+            db: Arc::new(db),
+            _secondary_dir: Some(Arc::new(secondary_dir)),
+        })
+    }
+
+    /// Starts an atomic write. Nothing is visible until `DbWrite::commit`.
+    pub fn write(&self) -> DbWrite<'_> {
+        DbWrite {
+            db: &self.db,
+            batch: WriteBatch::default(),
+            nodes: TreeNodes::new(&self.db),
+        }
+    }
+
+    /// The Sparse Merkle root over all accounts.
+    /// Matches `ZkMemStore::compute_root` and the root the Guest computes.
+    pub fn state_root(&self) -> Result<[u8; 32]> {
+        merkle::root(&TreeNodes::new(&self.db))
+    }
+
+    /// Height of the last committed batch (0 before the first one).
+    pub fn batch_height(&self) -> Result<u64> {
+        Ok(self.get_meta_u64(META_BATCH_HEIGHT)?.unwrap_or(0))
+    }
+
+    /// Sequence number of the last L1 deposit applied, if any.
+    pub fn l1_seq(&self) -> Result<Option<u64>> {
+        self.get_meta_u64(META_L1_SEQ)
+    }
+
+    /// The last L1 transaction the indexer is done with, if any.
+    pub fn indexer_cursor(&self) -> Result<Option<IndexerCursor>> {
+        match self
+            .db
+            .get_cf(cf(&self.db, CF_META)?, META_INDEXER_CURSOR)?
+        {
+            Some(bytes) => Ok(Some(wincode::deserialize::<IndexerCursor>(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// The receipt of the sealed transaction with hash `tx_hash`.
+    pub fn get_receipt(&self, tx_hash: &[u8; 32]) -> Result<Option<TxReceipt>> {
+        match self.db.get_cf(cf(&self.db, CF_RECEIPTS)?, tx_hash)? {
+            Some(bytes) => Ok(Some(wincode::deserialize::<TxReceipt>(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// The sealed batch at `height`.
+    pub fn get_batch(&self, height: u64) -> Result<Option<SealedBatch>> {
+        match self
+            .db
+            .get_cf(cf(&self.db, CF_BATCHES)?, height.to_be_bytes())?
+        {
+            Some(bytes) => Ok(Some(wincode::deserialize::<SealedBatch>(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Queued withdrawals of batches at or above `from_height`, in queue order.
+    pub fn withdrawals(&self, from_height: u64) -> Result<Vec<QueuedWithdrawal>> {
+        let start = withdrawal_key(from_height, 0);
+        let mut queued = Vec::new();
+        for entry in self.db.iterator_cf(
+            cf(&self.db, CF_WITHDRAWALS)?,
+            IteratorMode::From(&start, Direction::Forward),
+        ) {
+            let (_, value) = entry?;
+            queued.push(wincode::deserialize::<QueuedWithdrawal>(&value)?);
+        }
+        Ok(queued)
+    }
+
+    /// Ledger entries of deposits with `l1_seq` at or above `from_seq`, in `l1_seq` order.
+    pub fn deposits(&self, from_seq: u64) -> Result<Vec<ProcessedDeposit>> {
+        let start = from_seq.to_be_bytes();
+        let mut deposits = Vec::new();
+        for entry in self.db.iterator_cf(
+            cf(&self.db, CF_DEPOSITS)?,
+            IteratorMode::From(&start, Direction::Forward),
+        ) {
+            let (_, value) = entry?;
+            deposits.push(wincode::deserialize::<ProcessedDeposit>(&value)?);
+        }
+        Ok(deposits)
+    }
+
+    /// L1 deposits waiting for finality, in `l1_seq` order.
+    pub fn pending_deposits(&self) -> Result<Vec<PendingDeposit>> {
+        let mut deposits = Vec::new();
+        for entry in self
+            .db
+            .iterator_cf(cf(&self.db, CF_PENDING_DEPOSITS)?, IteratorMode::Start)
+        {
+            let (_, value) = entry?;
+            deposits.push(wincode::deserialize::<PendingDeposit>(&value)?);
+        }
+        Ok(deposits)
+    }
+
+    /// Proof records of batches at or above `from_height`, in height order.
+    pub fn proofs(&self, from_height: u64) -> Result<Vec<ProofRecord>> {
+        let start = from_height.to_be_bytes();
+        let mut records = Vec::new();
+        for entry in self.db.iterator_cf(
+            cf(&self.db, CF_PROOFS)?,
+            IteratorMode::From(&start, Direction::Forward),
+        ) {
+            let (_, value) = entry?;
+            records.push(wincode::deserialize::<ProofRecord>(&value)?);
+        }
+        Ok(records)
+    }
+
+    fn get_meta_u64(&self, key: &[u8]) -> Result<Option<u64>> {
+        match self.db.get_cf(cf(&self.db, CF_META)?, key)? {
+            Some(bytes) => {
+                let bytes: [u8; 8] = bytes.as_slice().try_into().context("Malformed metadata")?;
+                Ok(Some(u64::from_be_bytes(bytes)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Databases written before the tree existed have accounts but no nodes.
+    fn rebuild_tree_if_missing(&self) -> Result<()> {
+        if TreeNodes::new(&self.db).get_node(&NodeKey::ROOT)?.is_some() {
+            return Ok(());
+        }
+
+        let mut write = self.write();
+        for entry in self
+            .db
+            .iterator_cf(cf(&self.db, CF_ACCOUNTS)?, IteratorMode::Start)
+        {
+            let (key, value) = entry?;
+            let id = AccountId(key.as_ref().try_into().context("Malformed account key")?);
+            let data: AccountData = wincode::deserialize::<AccountState>(&value)?.into();
+            merkle::update(&mut write.nodes, &id, account_leaf(&id, &data))?;
+        }
+        write.commit()
     }
 }
 
 impl StateStore for RocksDbStore {
     fn get_account(&self, id: &AccountId) -> Result<AccountState> {
-        let cf = self
-            .db
-            .cf_handle(CF_ACCOUNTS)
-            .context("Column family 'accounts' missing")?;
-
         // Key is the 32-byte AccountId directly
-        match self.db.get_cf(cf, id.0)? {
+        match self.db.get_cf(cf(&self.db, CF_ACCOUNTS)?, id.0)? {
             Some(bytes) => {
                 let state: AccountState = wincode::deserialize::<AccountState>(&bytes)?;
                 Ok(state)
@@ -49,14 +245,356 @@ impl StateStore for RocksDbStore {
     }
 
     fn set_account(&mut self, id: AccountId, state: AccountState) -> Result<()> {
-        let cf = self
-            .db
-            .cf_handle(CF_ACCOUNTS)
-            .context("Column family 'accounts' missing")?;
+        let mut write = self.write();
+        write.set_account(id, state)?;
+        write.commit()
+    }
 
-        let bytes = wincode::serialize(&state)?;
+    fn set_accounts(&mut self, updates: Vec<(AccountId, AccountState)>) -> Result<()> {
+        let mut write = self.write();
+        for (id, state) in updates {
+            write.set_account(id, state)?;
+        }
+        write.commit()
+    }
+}
+
+impl ProvableStore for RocksDbStore {
+    fn state_root(&self) -> Result<[u8; 32]> {
+        RocksDbStore::state_root(self)
+    }
+
+    fn multiproof(&self, ids: &[AccountId]) -> Result<MultiProof> {
+        merkle::multiproof(&TreeNodes::new(&self.db), ids, |id| {
+            Ok(self.get_account(id)?.into())
+        })
+    }
+}
+
+/// A set of account, tree and metadata changes applied in one RocksDB `WriteBatch`.
+/// Dropping it without calling `commit` discards everything.
+pub struct DbWrite<'a> {
+    db: &'a DB,
+    batch: WriteBatch,
+    nodes: TreeNodes<'a>,
+}
+
+impl DbWrite<'_> {
+    /// Stages an account and the tree nodes above it.
+    pub fn set_account(&mut self, id: AccountId, state: AccountState) -> Result<()> {
+        self.batch
+            .put_cf(cf(self.db, CF_ACCOUNTS)?, id.0, wincode::serialize(&state)?);
 
-        self.db.put_cf(cf, id.0, bytes)?;
+        let data: AccountData = state.into();
+        merkle::update(&mut self.nodes, &id, account_leaf(&id, &data))
+    }
+
+    pub fn set_batch_height(&mut self, height: u64) -> Result<()> {
+        self.batch.put_cf(
+            cf(self.db, CF_META)?,
+            META_BATCH_HEIGHT,
+            height.to_be_bytes(),
+        );
         Ok(())
     }
+
+    pub fn set_l1_seq(&mut self, seq: u64) -> Result<()> {
+        self.batch
+            .put_cf(cf(self.db, CF_META)?, META_L1_SEQ, seq.to_be_bytes());
+        Ok(())
+    }
+
+    pub fn set_indexer_cursor(&mut self, cursor: &IndexerCursor) -> Result<()> {
+        self.batch.put_cf(
+            cf(self.db, CF_META)?,
+            META_INDEXER_CURSOR,
+            wincode::serialize(cursor)?,
+        );
+        Ok(())
+    }
+
+    /// Stages a receipt, keyed by its transaction hash.
+    pub fn put_receipt(&mut self, receipt: &TxReceipt) -> Result<()> {
+        self.batch.put_cf(
+            cf(self.db, CF_RECEIPTS)?,
+            receipt.tx_hash,
+            wincode::serialize(receipt)?,
+        );
+        Ok(())
+    }
+
+    pub fn put_batch(&mut self, batch: &SealedBatch) -> Result<()> {
+        self.batch.put_cf(
+            cf(self.db, CF_BATCHES)?,
+            batch.header.height.to_be_bytes(),
+            wincode::serialize(batch)?,
+        );
+        Ok(())
+    }
+
+    /// Stages a withdrawal queue entry.
+    pub fn put_withdrawal(&mut self, withdrawal: &QueuedWithdrawal) -> Result<()> {
+        self.batch.put_cf(
+            cf(self.db, CF_WITHDRAWALS)?,
+            withdrawal_key(withdrawal.batch_height, withdrawal.position),
+            wincode::serialize(withdrawal)?,
+        );
+        Ok(())
+    }
+
+    /// Stages a deposit ledger entry, keyed by its `l1_seq`.
+    pub fn put_deposit(&mut self, deposit: &ProcessedDeposit) -> Result<()> {
+        self.batch.put_cf(
+            cf(self.db, CF_DEPOSITS)?,
+            deposit.event.l1_seq.to_be_bytes(),
+            wincode::serialize(deposit)?,
+        );
+        Ok(())
+    }
+
+    /// Stages a pending deposit. After an L1 reorg, two transactions can claim one `l1_seq`.
+    pub fn put_pending_deposit(&mut self, deposit: &PendingDeposit) -> Result<()> {
+        self.batch.put_cf(
+            cf(self.db, CF_PENDING_DEPOSITS)?,
+            pending_deposit_key(deposit),
+            wincode::serialize(deposit)?,
+        );
+        Ok(())
+    }
+
+    pub fn delete_pending_deposit(&mut self, deposit: &PendingDeposit) -> Result<()> {
+        self.batch.delete_cf(
+            cf(self.db, CF_PENDING_DEPOSITS)?,
+            pending_deposit_key(deposit),
+        );
+        Ok(())
+    }
+
+    /// Stages a proof record, replacing the batch's previous one.
+    pub fn put_proof(&mut self, record: &ProofRecord) -> Result<()> {
+        self.batch.put_cf(
+            cf(self.db, CF_PROOFS)?,
+            record.batch_height.to_be_bytes(),
+            wincode::serialize(record)?,
+        );
+        Ok(())
+    }
+
+    /// State root including the staged account changes.
+    pub fn state_root(&self) -> Result<[u8; 32]> {
+        merkle::root(&self.nodes)
+    }
+
+    /// Writes everything staged so far atomically.
+    pub fn commit(mut self) -> Result<()> {
+        let nodes_cf = cf(self.db, CF_SMT_NODES)?;
+        for (key, node) in self.nodes.pending {
+            match node {
+                Some(node) => {
+                    self.batch
+                        .put_cf(nodes_cf, key.to_bytes(), wincode::serialize(&node)?)
+                }
+                None => self.batch.delete_cf(nodes_cf, key.to_bytes()),
+            }
+        }
+        self.db.write(self.batch)?;
+        Ok(())
+    }
+}
+
+/// Tree nodes kept in the `smt_nodes` column family, keyed by `NodeKey::to_bytes`.
+/// Writes stay in `pending` until the owning `DbWrite` commits them.
+struct TreeNodes<'a> {
+    db: &'a DB,
+    pending: HashMap<NodeKey, Option<TreeNode>>,
+}
+
+impl<'a> TreeNodes<'a> {
+    fn new(db: &'a DB) -> Self {
+        Self {
+            db,
+            pending: HashMap::new(),
+        }
+    }
+}
+
+impl NodeStore for TreeNodes<'_> {
+    type Error = anyhow::Error;
+
+    fn get_node(&self, key: &NodeKey) -> Result<Option<TreeNode>> {
+        if let Some(node) = self.pending.get(key) {
+            return Ok(node.clone());
+        }
+
+        match self.db.get_cf(cf(self.db, CF_SMT_NODES)?, key.to_bytes())? {
+            Some(bytes) => Ok(Some(wincode::deserialize::<TreeNode>(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn put_node(&mut self, key: NodeKey, node: Option<TreeNode>) -> Result<()> {
+        self.pending.insert(key, node);
+        Ok(())
+    }
+}
+
+/// Big-endian so that RocksDB's byte order is queue order.
+fn withdrawal_key(batch_height: u64, position: u32) -> [u8; 12] {
+    let mut key = [0u8; 12];
+    key[..8].copy_from_slice(&batch_height.to_be_bytes());
+    key[8..].copy_from_slice(&position.to_be_bytes());
+    key
+}
+
+/// `l1_seq` (big-endian) followed by the transaction signature.
+fn pending_deposit_key(deposit: &PendingDeposit) -> Vec<u8> {
+    let mut key = deposit.event.l1_seq.to_be_bytes().to_vec();
+    key.extend_from_slice(deposit.signature.as_bytes());
+    key
+}
+
+fn cf<'a>(db: &'a DB, name: &str) -> Result<&'a ColumnFamily> {
+    db.cf_handle(name)
+        .with_context(|| format!("Column family '{}' missing", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        std::collections::HashMap,
+        zelana_core::{test_utils::temp_path, DepositEvent},
+        zelana_execution::{TxStatus, ZkMemStore},
+    };
+
+    #[test]
+    fn test_state_root_matches_in_memory_store() {
+        let path = temp_path("state-root");
+        let mut db = RocksDbStore::open(&path).unwrap();
+        let mut mem = ZkMemStore::new(HashMap::new());
+        assert_eq!(db.state_root().unwrap(), mem.compute_root());
+
+        for i in 0..50u8 {
+            let state = AccountState {
+                balance: 10 * i as u64,
+                nonce: (i % 3) as u64,
+            };
+            db.set_account(AccountId([i; 32]), state.clone()).unwrap();
+            mem.set_account(AccountId([i; 32]), state).unwrap();
+        }
+        assert_eq!(db.state_root().unwrap(), mem.compute_root());
+
+        // Reopening keeps the persisted root
+        let root = db.state_root().unwrap();
+        drop(db);
+        let db = RocksDbStore::open(&path).unwrap();
+        assert_eq!(db.state_root().unwrap(), root);
+
+        drop(db);
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn test_write_is_atomic() {
+        let path = temp_path("atomic-write");
+        let mut db = RocksDbStore::open(&path).unwrap();
+        let mut mem = ZkMemStore::new(HashMap::new());
+
+        let mut write = db.write();
+        for i in 1..=5u8 {
+            let state = AccountState {
+                balance: i as u64,
+                nonce: 0,
+            };
+            write
+                .set_account(AccountId([i; 32]), state.clone())
+                .unwrap();
+            mem.set_account(AccountId([i; 32]), state).unwrap();
+        }
+        write.set_batch_height(7).unwrap();
+        write.set_l1_seq(42).unwrap();
+        let receipt = TxReceipt {
+            tx_hash: [9u8; 32],
+            index: 0,
+            status: TxStatus::Success,
+            balance_deltas: vec![],
+            events: vec![],
+        };
+        write.put_receipt(&receipt).unwrap();
+
+        // Nothing is visible before the commit
+        assert_eq!(db.get_account(&AccountId([1u8; 32])).unwrap().balance, 0);
+        assert_eq!(db.l1_seq().unwrap(), None);
+
+        write.commit().unwrap();
+        assert_eq!(db.get_account(&AccountId([3u8; 32])).unwrap().balance, 3);
+        assert_eq!(db.batch_height().unwrap(), 7);
+        assert_eq!(db.l1_seq().unwrap(), Some(42));
+        assert_eq!(db.get_receipt(&[9u8; 32]).unwrap(), Some(receipt));
+        assert_eq!(db.state_root().unwrap(), mem.compute_root());
+
+        // A dropped write leaves no trace
+        let mut write = db.write();
+        write
+            .set_account(AccountId([1u8; 32]), AccountState::default())
+            .unwrap();
+        drop(write);
+        assert_eq!(db.state_root().unwrap(), mem.compute_root());
+
+        db.set_accounts(vec![(
+            AccountId([6u8; 32]),
+            AccountState {
+                balance: 6,
+                nonce: 1,
+            },
+        )])
+        .unwrap();
+        mem.set_account(
+            AccountId([6u8; 32]),
+            AccountState {
+                balance: 6,
+                nonce: 1,
+            },
+        )
+        .unwrap();
+        assert_eq!(db.state_root().unwrap(), mem.compute_root());
+
+        drop(db);
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn test_secondary_reads_while_the_primary_is_open() {
+        let path = temp_path("secondary");
+        let db = RocksDbStore::open(&path).unwrap();
+        let deposit = PendingDeposit {
+            event: DepositEvent {
+                to: AccountId([1u8; 32]),
+                amount: 10,
+                l1_seq: 0,
+            },
+            signature: "sig".to_string(),
+            slot: 5,
+        };
+        let mut write = db.write();
+        write.put_pending_deposit(&deposit).unwrap();
+        write
+            .set_account(
+                AccountId([2u8; 32]),
+                AccountState {
+                    balance: 3,
+                    nonce: 0,
+                },
+            )
+            .unwrap();
+        write.commit().unwrap();
+
+        let secondary = RocksDbStore::open_secondary(&path).unwrap();
+        assert_eq!(secondary.pending_deposits().unwrap(), vec![deposit]);
+        assert_eq!(secondary.state_root().unwrap(), db.state_root().unwrap());
+
+        drop(secondary);
+        drop(db);
+        std::fs::remove_dir_all(&path).unwrap();
+    }
 }
@@ -1,50 +1,109 @@
 use {
-    crate::db::RocksDbStore,
+    crate::{
+        batch::{BatchBuilder, BatchConfig, SealedBatch},
+        db::RocksDbStore,
+    },
     anyhow::Result,
-    log::{error, info},
-    zelana_core::SignedTransaction,
-    zelana_execution::BatchExecutor,
+    log::{error, info, warn},
+    tokio::sync::Mutex,
+    zelana_core::DepositEvent,
+    zelana_execution::{ExecutionError, TxReceipt, TxStatus},
     zelana_sdk::L2Transaction,
 };
 
 pub struct TransactionExecutor {
     pub db: RocksDbStore,
+    /// Transactions signed for any other chain are rejected.
+    chain_id: u64,
+    batches: Mutex<BatchBuilder>,
 }
 
 impl TransactionExecutor {
-    pub fn new(db_path: &str) -> Result<Self> {
+    pub fn new(db_path: &str, chain_id: u64, config: BatchConfig) -> Result<Self> {
         let db = RocksDbStore::open(db_path)?;
-        Ok(Self { db })
+        let batches = Mutex::new(BatchBuilder::new(db.clone(), chain_id, config));
+        Ok(Self {
+            db,
+            chain_id,
+            batches,
+        })
     }
-    /// Takes a signed transaction, validates logic, and persists to DB.
-    pub async fn process(&self, tx: SignedTransaction) -> anyhow::Result<()> {
-        // SVM Execution
-        // 1. Load Account
-        // 2. Check Balance
-        // 3. Update State
+    /// Takes a signed transfer or withdrawal, validates logic, and adds it to the open batch.
+    /// State is persisted when the batch is sealed. A transaction that fails on execution
+    /// (e.g. insufficient funds) is still added, with a `Failed` receipt.
+    pub async fn process(&self, tx: L2Transaction) -> Result<TxReceipt, ExecutionError> {
+        let chain_id = match &tx {
+            L2Transaction::Transfer(signed_tx) => signed_tx.data.chain_id,
+            L2Transaction::Withdraw(req) => req.data.chain_id,
+            L2Transaction::Deposit(_) => return Err(ExecutionError::Unsupported),
+        };
+        if chain_id != self.chain_id {
+            return Err(ExecutionError::WrongChain {
+                expected: self.chain_id,
+                got: chain_id,
+            });
+        }
 
-        let mut store = self.db.clone();
+        self.execute(tx).await
+    }
 
-        //wrap in the execution engin
-        let mut executor = BatchExecutor::new(&mut store);
+    /// Adds a deposit seen on L1 to the open batch. Deposits carry no signature;
+    /// the indexer vouches for them.
+    pub async fn process_deposit(&self, event: DepositEvent) -> Result<TxReceipt, ExecutionError> {
+        self.execute(L2Transaction::Deposit(event)).await
+    }
 
-        //wrap as l2transaction
-        let l2_tx = L2Transaction::Transfer(tx.clone());
+    /// Seals the open batch once it is older than the configured age.
+    pub async fn seal_if_due(&self) -> Result<()> {
+        if let Some(batch) = self.batches.lock().await.seal_if_due()? {
+            log_sealed(&batch);
+        }
+        Ok(())
+    }
 
-        match executor.execute(&l2_tx) {
-            Ok(_) => {
-                info!(
-                    "COMMITTED: {} -> {} | Amt: {}",
-                    tx.data.from.to_hex(),
-                    tx.data.to.to_hex(),
-                    tx.data.amount
-                );
-                Ok(())
+    async fn execute(&self, tx: L2Transaction) -> Result<TxReceipt, ExecutionError> {
+        let mut batches = self.batches.lock().await;
+        match batches.execute(tx.clone()) {
+            Ok((receipt, sealed)) => {
+                match (&receipt.status, &tx) {
+                    (TxStatus::Failed(e), _) => warn!("FAILED (code {}): {}", e.code(), e),
+                    (TxStatus::Success, L2Transaction::Transfer(signed_tx)) => info!(
+                        "COMMITTED: {} -> {} | Amt: {}",
+                        signed_tx.data.from.to_hex(),
+                        signed_tx.data.to.to_hex(),
+                        signed_tx.data.amount
+                    ),
+                    (TxStatus::Success, L2Transaction::Withdraw(req)) => info!(
+                        "COMMITTED: {} -> L1 {} | Amt: {}",
+                        req.data.from.to_hex(),
+                        hex::encode(req.data.to_l1_address),
+                        req.data.amount
+                    ),
+                    (TxStatus::Success, L2Transaction::Deposit(event)) => info!(
+                        "DEPOSIT: +{} for {} | l1_seq {}",
+                        event.amount,
+                        event.to.to_hex(),
+                        event.l1_seq
+                    ),
+                }
+                if let Some(batch) = sealed {
+                    log_sealed(&batch);
+                }
+                Ok(receipt)
             }
             Err(e) => {
-                error!("REVERTED: {}", e);
+                error!("REVERTED (code {}): {}", e.code(), e);
                 Err(e)
             }
         }
     }
 }
+
+fn log_sealed(batch: &SealedBatch) {
+    info!(
+        "SEALED: batch {} | {} txs | root {}",
+        batch.header.height,
+        batch.input.transactions.len(),
+        hex::encode(batch.header.post_state_root)
+    );
+}
@@ -1,124 +1,746 @@
 use {
-    crate::db::RocksDbStore,
+    crate::executor::TransactionExecutor,
+    anyhow::{Context, Result},
+    base64::prelude::{Engine, BASE64_STANDARD},
     log::{error, info, warn},
     solana_client::{
-        nonblocking::pubsub_client::PubsubClient,
-        rpc_config::{RpcTransactionLogsConfig, RpcTransactionLogsFilter},
+        nonblocking::{pubsub_client::PubsubClient, rpc_client::RpcClient},
+        rpc_client::GetConfirmedSignaturesForAddress2Config,
+        rpc_config::{RpcTransactionConfig, RpcTransactionLogsConfig, RpcTransactionLogsFilter},
     },
     solana_commitment_config::CommitmentConfig,
     solana_pubkey::Pubkey,
-    std::str::FromStr,
+    solana_signature::Signature,
+    std::{
+        collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+        env,
+        str::FromStr,
+        sync::Arc,
+        time::{Duration, Instant},
+    },
+    thiserror::Error,
     tokio_stream::StreamExt,
-    zelana_core::{AccountId, DepositEvent},
-    zelana_execution::StateStore,
+    wincode::{SchemaRead, SchemaWrite},
+    zelana_core::{BridgeEvent, BridgeEventError, DepositEvent, PendingDeposit},
+    zelana_execution::ExecutionError,
 };
 
-pub async fn start_indexer(db: RocksDbStore, ws_url: String, bridge_program_id: String) {
-    info!("🔭 Indexer started. Watching: {}", bridge_program_id);
+/// Signatures requested per page of bridge history (the RPC maximum).
+const BACKFILL_PAGE: usize = 1000;
+/// Signatures per `getSignatureStatuses` request (the RPC maximum).
+const STATUS_PAGE: usize = 256;
+
+/// The last L1 transaction the indexer is done with, persisted in the `meta` column family.
+#[derive(Debug, Clone, PartialEq, Eq, SchemaRead, SchemaWrite)]
+pub struct IndexerCursor {
+    pub slot: u64,
+    /// Base58 signature of the transaction.
+    pub signature: String,
+}
 
-    let pubsub = match PubsubClient::new(&ws_url).await {
-        Ok(client) => client,
-        Err(e) => {
-            error!("Failed to connect to Solana WSS: {}", e);
-            return;
+/// When an L1 deposit is final enough to credit on L2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Finality {
+    /// Once its slot is rooted.
+    Finalized,
+    /// Once this many blocks voted on top of it (or it is rooted). Not safe against
+    /// deeper reorgs: a deposit credited and then reorged out stays credited.
+    Confirmations(usize),
+}
+
+impl Finality {
+    /// Whether a transaction with `confirmations` meets the rule. The RPC reports
+    /// `None` confirmations for rooted transactions.
+    fn reached(self, confirmations: Option<usize>) -> bool {
+        match (self, confirmations) {
+            (_, None) => true,
+            (Finality::Finalized, Some(_)) => false,
+            (Finality::Confirmations(needed), Some(confirmations)) => confirmations >= needed,
         }
-    };
+    }
+}
+
+impl FromStr for Finality {
+    type Err = anyhow::Error;
 
-    info!("{:?}", pubsub);
+    /// `finalized`, or a number of confirmations.
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "finalized" => Ok(Finality::Finalized),
+            n => Ok(Finality::Confirmations(n.parse().with_context(|| {
+                format!(
+                    "Expected 'finalized' or a number of confirmations, got '{}'",
+                    n
+                )
+            })?)),
+        }
+    }
+}
+
+/// Where the indexer reads the bridge from and how it reconnects.
+#[derive(Debug, Clone)]
+pub struct IndexerConfig {
+    pub ws_url: String,
+    /// HTTP RPC endpoint used to backfill missed transactions.
+    pub rpc_url: String,
+    pub bridge_program_id: Pubkey,
+    /// When pending deposits are credited.
+    pub finality: Finality,
+    /// How often pending deposits are checked against the L1 chain.
+    pub poll_interval: Duration,
+    /// Wait before the first reconnect; doubled after each failed run.
+    pub min_backoff: Duration,
+    pub max_backoff: Duration,
+}
 
-    let (mut stream, _unsub) = match pubsub
-        .logs_subscribe(
-            RpcTransactionLogsFilter::Mentions(vec![bridge_program_id]),
-            RpcTransactionLogsConfig {
-                commitment: Some(CommitmentConfig::confirmed()),
+impl IndexerConfig {
+    /// Reads `BRIDGE_PROGRAM_ID`, `SOLANA_WSS_URL`, `SOLANA_RPC_URL`, `DEPOSIT_FINALITY`,
+    /// `DEPOSIT_POLL_MS`, `INDEXER_MIN_BACKOFF_MS` and `INDEXER_MAX_BACKOFF_MS`, falling
+    /// back to a local validator and crediting finalized deposits.
+    pub fn from_env() -> Result<Self> {
+        let bridge_program_id = env::var("BRIDGE_PROGRAM_ID")
+            .unwrap_or_else(|_| "DouWDzYTAxi5c3ui695xqozJuP9SpAutDcTbyQnkAguo".to_string());
+        Ok(Self {
+            ws_url: env::var("SOLANA_WSS_URL")
+                .unwrap_or_else(|_| "ws://127.0.0.1:8900".to_string()),
+            rpc_url: env::var("SOLANA_RPC_URL")
+                .unwrap_or_else(|_| "http://127.0.0.1:8899".to_string()),
+            bridge_program_id: Pubkey::from_str(&bridge_program_id)
+                .with_context(|| format!("Invalid BRIDGE_PROGRAM_ID {}", bridge_program_id))?,
+            finality: match env::var("DEPOSIT_FINALITY") {
+                Ok(finality) => finality.parse().context("Invalid DEPOSIT_FINALITY")?,
+                Err(_) => Finality::Finalized,
             },
-        )
-        .await
-    {
-        Ok(s) => s,
-        Err(e) => {
-            error!("Failed to subscribe to logs: {}", e);
-            return;
+            poll_interval: env::var("DEPOSIT_POLL_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .map(Duration::from_millis)
+                .unwrap_or(Duration::from_secs(2)),
+            min_backoff: env::var("INDEXER_MIN_BACKOFF_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .map(Duration::from_millis)
+                .unwrap_or(Duration::from_millis(500)),
+            max_backoff: env::var("INDEXER_MAX_BACKOFF_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .map(Duration::from_millis)
+                .unwrap_or(Duration::from_secs(30)),
+        })
+    }
+}
+
+/// Keeps the indexer running. Every run resumes from the persisted cursor; when one
+/// disconnects, fails or panics, the next starts after an exponential backoff.
+pub async fn supervise(executor: Arc<TransactionExecutor>, config: IndexerConfig) {
+    let mut backoff = config.min_backoff;
+    loop {
+        let started = Instant::now();
+        let indexer = Indexer::new(executor.clone(), config.clone());
+        match tokio::spawn(indexer.run()).await {
+            Ok(Ok(())) => warn!("Indexer stream closed"),
+            Ok(Err(e)) => error!("Indexer failed: {:#}", e),
+            Err(e) => error!("Indexer crashed: {}", e),
         }
-    };
 
-    while let Some(response) = stream.next().await {
-        for log in response.value.logs {
-            // Check for our specific log prefix
-            info!("{}", log);
-            if let Some(payload) = log.strip_prefix("Program log: ZE_DEPOSIT:") {
-                info!("{}", payload);
-                if let Some(event) = parse_deposit_log(payload) {
-                    info!("{:?}", event);
-                    process_deposit(&db, event);
+        // A run that stayed up for a while was not part of a failure streak
+        if started.elapsed() > config.max_backoff {
+            backoff = config.min_backoff;
+        }
+        info!("Restarting indexer in {:?}", backoff);
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(config.max_backoff);
+    }
+}
+
+/// One connection's worth of indexing: backfill from the cursor, then stream.
+///
+/// Deposits are seen at `confirmed` commitment and kept pending until they meet the
+/// `Finality` rule; only then are they credited.
+struct Indexer {
+    executor: Arc<TransactionExecutor>,
+    config: IndexerConfig,
+    /// Deposits waiting for finality, in the order they were seen
+    pending: Vec<PendingDeposit>,
+    /// Final deposits not applied yet: ones that arrived ahead of a missing l1_seq,
+    /// applied once the gap is filled, and ones that hit a storage error, retried each
+    /// poll
+    held: BTreeMap<u64, DepositEvent>,
+    checkpoints: Checkpoints,
+}
+
+impl Indexer {
+    fn new(executor: Arc<TransactionExecutor>, config: IndexerConfig) -> Self {
+        Self {
+            executor,
+            config,
+            pending: Vec::new(),
+            held: BTreeMap::new(),
+            checkpoints: Checkpoints::default(),
+        }
+    }
+
+    async fn run(mut self) -> Result<()> {
+        // Pending deposits of a previous run are checked again; backfill finds the rest
+        self.pending = self.executor.db.pending_deposits()?;
+        let cursor = self.executor.db.indexer_cursor()?;
+        match &cursor {
+            Some(cursor) => info!(
+                "Resuming indexer after {} (slot {})",
+                cursor.signature, cursor.slot
+            ),
+            None => info!("No indexer cursor, indexing the whole bridge history"),
+        }
+
+        // Subscribe before backfilling so nothing lands between the two
+        let pubsub = PubsubClient::new(&self.config.ws_url)
+            .await
+            .context("Failed to connect to Solana WSS")?;
+        let (mut stream, _unsub) = pubsub
+            .logs_subscribe(
+                RpcTransactionLogsFilter::Mentions(vec![self.config.bridge_program_id.to_string()]),
+                RpcTransactionLogsConfig {
+                    commitment: Some(CommitmentConfig::confirmed()),
+                },
+            )
+            .await
+            .context("Failed to subscribe to logs")?;
+
+        let rpc = RpcClient::new_with_commitment(
+            self.config.rpc_url.clone(),
+            CommitmentConfig::confirmed(),
+        );
+        let backfilled = self.backfill(&rpc, cursor).await?;
+
+        info!(
+            "🔭 Indexer streaming. Watching: {}",
+            self.config.bridge_program_id
+        );
+        let mut ticker = tokio::time::interval(self.config.poll_interval);
+        loop {
+            tokio::select! {
+                response = stream.next() => {
+                    let Some(response) = response else {
+                        return Ok(());
+                    };
+                    let log = response.value;
+                    if backfilled.contains(&log.signature) {
+                        continue;
+                    }
+                    let logs = if log.err.is_some() {
+                        Vec::new()
+                    } else {
+                        log.logs
+                    };
+                    let at = IndexerCursor {
+                        slot: response.context.slot,
+                        signature: log.signature,
+                    };
+                    self.index(at, &logs)?;
+                }
+                _ = ticker.tick() => {
+                    self.settle(&rpc).await?;
+                    // Deposits held back by a storage error are retried
+                    self.apply_held().await;
+                }
+            }
+        }
+    }
+
+    /// Indexes the bridge transactions after `cursor` from RPC history, oldest first.
+    /// Returns their signatures so the live stream can skip them.
+    async fn backfill(
+        &mut self,
+        rpc: &RpcClient,
+        cursor: Option<IndexerCursor>,
+    ) -> Result<HashSet<String>> {
+        let commitment = rpc.commitment();
+        let until = cursor
+            .map(|cursor| Signature::from_str(&cursor.signature))
+            .transpose()
+            .context("Malformed indexer cursor")?;
+
+        // History comes newest first, a page at a time
+        let mut missed = Vec::new();
+        let mut before = None;
+        loop {
+            let page = rpc
+                .get_signatures_for_address_with_config(
+                    &self.config.bridge_program_id,
+                    GetConfirmedSignaturesForAddress2Config {
+                        before,
+                        until,
+                        limit: Some(BACKFILL_PAGE),
+                        commitment: Some(commitment),
+                    },
+                )
+                .await
+                .context("Failed to fetch bridge history")?;
+            let Some(oldest) = page.last() else {
+                break;
+            };
+            before = Some(Signature::from_str(&oldest.signature)?);
+            let more = page.len() == BACKFILL_PAGE;
+            missed.extend(page);
+            if !more {
+                break;
+            }
+        }
+        if !missed.is_empty() {
+            info!("Backfilling {} bridge transaction(s)", missed.len());
+        }
+
+        let mut backfilled = HashSet::new();
+        for status in missed.into_iter().rev() {
+            let logs = if status.err.is_some() {
+                Vec::new()
+            } else {
+                let tx = rpc
+                    .get_transaction_with_config(
+                        &Signature::from_str(&status.signature)?,
+                        RpcTransactionConfig {
+                            encoding: None,
+                            commitment: Some(commitment),
+                            max_supported_transaction_version: Some(0),
+                        },
+                    )
+                    .await
+                    .with_context(|| format!("Failed to fetch transaction {}", status.signature))?;
+                tx.transaction
+                    .meta
+                    .and_then(|meta| Option::<Vec<String>>::from(meta.log_messages))
+                    .unwrap_or_default()
+            };
+            let at = IndexerCursor {
+                slot: status.slot,
+                signature: status.signature.clone(),
+            };
+            self.index(at, &logs)?;
+            backfilled.insert(status.signature);
+        }
+        Ok(backfilled)
+    }
+
+    /// Records the deposits one L1 transaction logged as pending, then saves the cursor
+    /// as far as the sealed batches allow.
+    fn index(&mut self, at: IndexerCursor, logs: &[String]) -> Result<()> {
+        let deposits = deposits_in(logs, &self.config.bridge_program_id);
+        let last_l1_seq = deposits.iter().map(|event| event.l1_seq).max();
+
+        // A restart reloads pending deposits before backfill sees them again
+        if !self.pending.iter().any(|d| d.signature == at.signature) {
+            let mut write = self.executor.db.write();
+            for event in deposits {
+                let deposit = PendingDeposit {
+                    event,
+                    signature: at.signature.clone(),
+                    slot: at.slot,
+                };
+                info!(
+                    "Pending deposit l1_seq {} in slot {}",
+                    deposit.event.l1_seq, deposit.slot
+                );
+                write.put_pending_deposit(&deposit)?;
+                self.pending.push(deposit);
+            }
+            write.commit()?;
+        }
+
+        self.checkpoints.push(at, last_l1_seq);
+        self.save_cursor()
+    }
+
+    /// Credits the pending deposits whose transactions meet the finality rule and
+    /// drops the ones that left the canonical L1 chain.
+    async fn settle(&mut self, rpc: &RpcClient) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let finalized_slot = rpc
+            .get_slot_with_commitment(CommitmentConfig::finalized())
+            .await
+            .context("Failed to fetch the finalized slot")?;
+
+        // A transaction with several deposits is looked up once
+        let signatures: BTreeSet<&str> =
+            self.pending.iter().map(|d| d.signature.as_str()).collect();
+        let signatures: Vec<&str> = signatures.into_iter().collect();
+        let mut statuses = HashMap::new();
+        for chunk in signatures.chunks(STATUS_PAGE) {
+            let parsed = chunk
+                .iter()
+                .map(|signature| Signature::from_str(signature))
+                .collect::<Result<Vec<_>, _>>()?;
+            let page = rpc
+                .get_signature_statuses_with_history(&parsed)
+                .await
+                .context("Failed to fetch deposit statuses")?
+                .value;
+            statuses.extend(chunk.iter().map(|s| s.to_string()).zip(page));
+        }
+
+        let mut write = self.executor.db.write();
+        let mut credited = Vec::new();
+        let mut still_pending = Vec::new();
+        for mut deposit in std::mem::take(&mut self.pending) {
+            // The deposits of one transaction share its status
+            match statuses.get(&deposit.signature).and_then(Option::as_ref) {
+                Some(status) if status.err.is_some() => {
+                    warn!(
+                        "Dropping deposit l1_seq {}: transaction {} failed",
+                        deposit.event.l1_seq, deposit.signature
+                    );
+                    self.checkpoints.forget(&deposit.signature);
+                    write.delete_pending_deposit(&deposit)?;
+                }
+                Some(status) if self.config.finality.reached(status.confirmations) => {
+                    write.delete_pending_deposit(&deposit)?;
+                    credited.push(deposit.event);
+                }
+                Some(status) => {
+                    // It may have landed in another fork
+                    deposit.slot = status.slot;
+                    still_pending.push(deposit);
+                }
+                None if finalized_slot >= deposit.slot => {
+                    warn!(
+                        "Dropping deposit l1_seq {}: transaction {} left the L1 chain",
+                        deposit.event.l1_seq, deposit.signature
+                    );
+                    self.checkpoints.forget(&deposit.signature);
+                    write.delete_pending_deposit(&deposit)?;
+                }
+                None => still_pending.push(deposit),
+            }
+        }
+        self.pending = still_pending;
+        write.commit()?;
+
+        for event in credited {
+            self.apply(event).await;
+        }
+        self.save_cursor()
+    }
+
+    fn save_cursor(&mut self) -> Result<()> {
+        if let Some(cursor) = self.checkpoints.sealed(self.executor.db.l1_seq()?) {
+            let mut write = self.executor.db.write();
+            write.set_indexer_cursor(&cursor)?;
+            write.commit()?;
+        }
+        Ok(())
+    }
+
+    async fn apply(&mut self, event: DepositEvent) {
+        self.held.insert(event.l1_seq, event);
+        self.apply_held().await;
+    }
+
+    /// Applies held deposits in `l1_seq` order until one can't be applied yet.
+    async fn apply_held(&mut self) {
+        // Deposits go into the open batch like any other transaction, so the Guest proves them.
+        // They must arrive in l1_seq order; the executor rejects duplicates and gaps.
+        let held = &mut self.held;
+        while let Some((l1_seq, event)) = held.pop_first() {
+            match self.executor.process_deposit(event.clone()).await {
+                // Included as failed: its l1_seq is used up, so later deposits go on
+                Ok(receipt) if !receipt.is_success() => error!(
+                    "Deposit l1_seq {} could not be credited, its funds stay in the bridge",
+                    l1_seq
+                ),
+                Ok(_) => {}
+                Err(ExecutionError::DepositOutOfOrder { expected, got }) if got > expected => {
+                    warn!(
+                        "Deposit gap: l1_seq {}..{} missing, holding {} deposit(s)",
+                        expected,
+                        got,
+                        held.len() + 1
+                    );
+                    held.insert(l1_seq, event);
+                    break;
+                }
+                Err(ExecutionError::DepositOutOfOrder { .. }) => {
+                    info!("Skipping duplicate deposit l1_seq {}", l1_seq)
+                }
+                Err(ExecutionError::Storage(e)) => {
+                    // Says nothing about the deposit: retried on the next poll
+                    error!("Failed to apply deposit l1_seq {}: {}", l1_seq, e);
+                    held.insert(l1_seq, event);
+                    break;
+                }
+                Err(e) => {
+                    // Would fail the same way on every retry
+                    error!(
+                        "Dropping deposit l1_seq {} (code {}): {}",
+                        l1_seq,
+                        e.code(),
+                        e
+                    );
                 }
             }
         }
     }
 }
 
-/// Parses format: "ZE_DEPOSIT:<Pubkey>:<Amount>:<Nonce>"
-fn parse_deposit_log(payload: &str) -> Option<DepositEvent> {
-    let parts: Vec<&str> = payload.split(':').collect();
-    if parts.len() != 3 {
-        warn!("Malformed deposit log: {}", payload);
-        return None;
-    }
-    let pubkey_str = parts[0];
-    let pubkey = parse_log_pubkey(pubkey_str)?;
-
-    let amount = parts[1].parse::<u64>().ok()?;
-    let nonce = parts[2].parse::<u64>().ok()?;
-
-    info!("hi");
-    Some(DepositEvent {
-        to: map_l1_to_l2(pubkey), // We need this mapping function
-        amount,
-        l1_seq: nonce,
-    })
+/// L1 transactions indexed since the cursor was last saved, oldest first, each with
+/// the highest `l1_seq` it deposited.
+///
+/// Deposits are only durable once their batch is sealed, so the cursor never moves
+/// past a transaction whose deposits may still be pending or in the open batch. A
+/// restart replays those, and deposits that did get sealed are rejected as duplicates.
+#[derive(Debug, Default)]
+struct Checkpoints {
+    unsealed: VecDeque<(IndexerCursor, Option<u64>)>,
 }
 
-fn process_deposit(db: &RocksDbStore, event: DepositEvent) {
-    // 1. Load Account (or create new)
-    let mut account = db.get_account(&event.to).unwrap_or_default();
+impl Checkpoints {
+    fn push(&mut self, cursor: IndexerCursor, last_l1_seq: Option<u64>) {
+        self.unsealed.push_back((cursor, last_l1_seq));
+    }
 
-    // 2. Credit Balance
-    account.balance = account.balance.saturating_add(event.amount);
+    /// A transaction that left the L1 chain no longer holds the cursor back.
+    fn forget(&mut self, signature: &str) {
+        for (cursor, last_l1_seq) in &mut self.unsealed {
+            if cursor.signature == signature {
+                *last_l1_seq = None;
+            }
+        }
+    }
 
-    // 3. Save
-    // Note: In production, store the 'l1_seq' to prevent re-processing the same deposit!
-    // For MVP, direct addition is fine.
-    let mut db_mut = db.clone(); // Clone Arc for mutability trait
-    if let Err(e) = db_mut.set_account(event.to, account) {
-        error!("Failed to persist deposit: {}", e);
-    } else {
-        info!("DEPOSIT: +{} for {:?}", event.amount, event.to);
+    /// Drops the leading transactions whose deposits are sealed up to `sealed_l1_seq`
+    /// and returns the newest of them, if any.
+    fn sealed(&mut self, sealed_l1_seq: Option<u64>) -> Option<IndexerCursor> {
+        let mut newest = None;
+        while let Some((_, last_l1_seq)) = self.unsealed.front() {
+            // `None` orders first: a transaction without deposits never holds the cursor back
+            if *last_l1_seq > sealed_l1_seq {
+                break;
+            }
+            newest = self.unsealed.pop_front().map(|(cursor, _)| cursor);
+        }
+        newest
     }
 }
 
-fn parse_log_pubkey(log_val: &str) -> Option<Pubkey> {
-    let log_val = log_val.trim();
-
-    if log_val.starts_with('[') {
-        let bytes_str = log_val.trim_matches(|c| c == '[' || c == ']');
-        let bytes: Result<Vec<u8>, _> = bytes_str
-            .split(',')
-            .map(|s| s.trim().parse::<u8>())
-            .collect();
+/// Why a `Program data:` entry logged by the bridge is not a bridge event.
+#[derive(Debug, Error)]
+enum EventLogError {
+    #[error("Bridge event data has {0} fields, expected 1")]
+    FieldCount(usize),
+    #[error("Bridge event data is not base64: {0}")]
+    Encoding(#[from] base64::DecodeError),
+    #[error(transparent)]
+    Event(#[from] BridgeEventError),
+}
 
-        if let Ok(vec) = bytes {
-            if vec.len() == 32 {
-                return Some(Pubkey::new_from_array(vec.try_into().unwrap()));
+/// The deposits the bridge program emitted in a transaction's logs, in log order.
+///
+/// Only `Program data:` entries logged while the bridge itself is executing count. The
+/// invoke stack is followed through the runtime's `invoke`, `success` and `failed`
+/// lines, so another program in the transaction can't spoof a deposit.
+fn deposits_in(logs: &[String], bridge_program_id: &Pubkey) -> Vec<DepositEvent> {
+    let bridge = bridge_program_id.to_string();
+    let mut stack = Vec::new();
+    let mut deposits = Vec::new();
+    for log in logs {
+        if let Some(data) = log.strip_prefix("Program data: ") {
+            if stack.last() != Some(&bridge.as_str()) {
+                continue;
+            }
+            match decode_event(data) {
+                Ok(BridgeEvent::Deposit(event)) => deposits.push(event),
+                Err(e) => warn!("Skipping bridge event: {}", e),
+            }
+        } else if let Some(rest) = log.strip_prefix("Program ") {
+            let mut words = rest.split_whitespace();
+            match (words.next(), words.next()) {
+                (Some(program), Some("invoke")) => stack.push(program),
+                (Some(_), Some("success" | "failed:")) => {
+                    stack.pop();
+                }
+                _ => {}
             }
         }
     }
+    deposits
+}
 
-    Pubkey::from_str(log_val).ok()
+/// `sol_log_data` logs each field in base64; the bridge emits an event as one field.
+fn decode_event(data: &str) -> Result<BridgeEvent, EventLogError> {
+    let fields: Vec<&str> = data.split_whitespace().collect();
+    let [field] = fields.as_slice() else {
+        return Err(EventLogError::FieldCount(fields.len()));
+    };
+    Ok(BridgeEvent::decode(&BASE64_STANDARD.decode(field)?)?)
 }
 
-// Temporary MVP Mapping: L1 Pubkey bytes -> L2 Account ID
-fn map_l1_to_l2(l1_key: Pubkey) -> AccountId {
-    let mut bytes = [0u8; 32];
-    bytes.copy_from_slice(l1_key.as_ref());
-    AccountId(bytes)
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::batch::BatchConfig,
+        zelana_core::{test_utils::temp_path, AccountId},
+        zelana_execution::{AccountState, StateStore, TxStatus},
+    };
+
+    fn at(slot: u64) -> IndexerCursor {
+        IndexerCursor {
+            slot,
+            signature: format!("sig{}", slot),
+        }
+    }
+
+    #[test]
+    fn test_cursor_waits_for_sealed_deposits() {
+        let mut checkpoints = Checkpoints::default();
+        checkpoints.push(at(1), None);
+        checkpoints.push(at(2), Some(0));
+        checkpoints.push(at(3), None);
+        checkpoints.push(at(4), Some(2));
+
+        // Nothing sealed: only the leading transaction without deposits is done
+        assert_eq!(checkpoints.sealed(None), Some(at(1)));
+        assert_eq!(checkpoints.sealed(None), None);
+        // Deposit 0 sealed, deposit 2 still open
+        assert_eq!(checkpoints.sealed(Some(1)), Some(at(3)));
+        assert_eq!(checkpoints.sealed(Some(2)), Some(at(4)));
+        assert_eq!(checkpoints.sealed(Some(2)), None);
+
+        // A dropped transaction stops holding back the ones after it
+        checkpoints.push(at(5), Some(3));
+        checkpoints.push(at(6), None);
+        assert_eq!(checkpoints.sealed(Some(2)), None);
+        checkpoints.forget("sig5");
+        assert_eq!(checkpoints.sealed(Some(2)), Some(at(6)));
+    }
+
+    #[tokio::test]
+    async fn test_uncreditable_deposit_does_not_hold_up_later_ones() {
+        let path = temp_path("indexer-deposits");
+        let config = BatchConfig {
+            max_txs: 100,
+            max_age: Duration::ZERO,
+        };
+        let executor =
+            Arc::new(TransactionExecutor::new(path.to_str().unwrap(), 1, config).unwrap());
+        let alice = AccountId([1u8; 32]);
+        let bob = AccountId([2u8; 32]);
+        executor
+            .db
+            .clone()
+            .set_account(
+                alice,
+                AccountState {
+                    balance: u64::MAX,
+                    nonce: 0,
+                },
+            )
+            .unwrap();
+
+        let config = IndexerConfig {
+            ws_url: String::new(),
+            rpc_url: String::new(),
+            bridge_program_id: Pubkey::default(),
+            finality: Finality::Finalized,
+            poll_interval: Duration::from_secs(1),
+            min_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(1),
+        };
+        let mut indexer = Indexer::new(executor.clone(), config);
+        // Crediting alice overflows her balance, however often it is retried
+        indexer
+            .apply(DepositEvent {
+                to: alice,
+                amount: 1,
+                l1_seq: 0,
+            })
+            .await;
+        indexer
+            .apply(DepositEvent {
+                to: bob,
+                amount: 5,
+                l1_seq: 1,
+            })
+            .await;
+        assert!(indexer.held.is_empty());
+
+        executor.seal_if_due().await.unwrap();
+        let db = &executor.db;
+        assert_eq!(db.l1_seq().unwrap(), Some(1));
+        assert_eq!(db.get_account(&alice).unwrap().balance, u64::MAX);
+        assert_eq!(db.get_account(&bob).unwrap().balance, 5);
+        let deposits = db.deposits(0).unwrap();
+        assert_eq!(deposits.len(), 2);
+        assert_eq!(
+            db.get_receipt(&deposits[0].tx_hash)
+                .unwrap()
+                .unwrap()
+                .status,
+            TxStatus::Failed(ExecutionError::Overflow)
+        );
+
+        drop(indexer);
+        drop(executor);
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn test_finality_rules() {
+        assert_eq!(
+            "finalized".parse::<Finality>().unwrap(),
+            Finality::Finalized
+        );
+        assert_eq!(
+            "12".parse::<Finality>().unwrap(),
+            Finality::Confirmations(12)
+        );
+        assert!("soon".parse::<Finality>().is_err());
+
+        // Rooted transactions report no confirmation count
+        assert!(Finality::Finalized.reached(None));
+        assert!(!Finality::Finalized.reached(Some(31)));
+        assert!(Finality::Confirmations(12).reached(Some(12)));
+        assert!(!Finality::Confirmations(12).reached(Some(11)));
+        assert!(Finality::Confirmations(12).reached(None));
+    }
+
+    #[test]
+    fn test_deposits_only_from_bridge_events() {
+        let bridge = Pubkey::new_from_array([1u8; 32]);
+        let other = Pubkey::new_from_array([2u8; 32]);
+        let event = DepositEvent {
+            to: AccountId([3u8; 32]),
+            amount: 250,
+            l1_seq: 7,
+        };
+        let data = format!(
+            "Program data: {}",
+            BASE64_STANDARD.encode(BridgeEvent::Deposit(event.clone()).encode())
+        );
+        let logs = vec![
+            format!("Program {} invoke [1]", bridge),
+            "Program log: Instruction: Deposit".to_string(),
+            data.clone(),
+            // Malformed event data from the bridge is skipped
+            "Program data: AQA=".to_string(),
+            "Program data: not-base64!".to_string(),
+            format!("Program {} invoke [2]", other),
+            // The same bytes from another program are not a deposit
+            data.clone(),
+            format!("Program {} success", other),
+            format!("Program {} consumed 4000 of 200000 compute units", bridge),
+            format!("Program {} success", bridge),
+            data,
+        ];
+        assert_eq!(deposits_in(&logs, &bridge), vec![event]);
+
+        assert!(matches!(
+            decode_event("AQA="),
+            Err(EventLogError::Event(BridgeEventError::BadLength { .. }))
+        ));
+        assert!(matches!(
+            decode_event("AQA= AQA="),
+            Err(EventLogError::FieldCount(2))
+        ));
+    }
 }
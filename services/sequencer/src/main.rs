@@ -1,24 +1,43 @@
+mod batch;
 mod db;
 mod executor;
 mod ingest;
+mod proving;
 mod session;
 
 use {
+    batch::BatchConfig,
     executor::TransactionExecutor,
     log::{debug, error, info, warn},
+    proving::{ProofStatus, ProverConfig, ProverService},
     session::SessionManager,
-    std::{env, sync::Arc},
+    std::{env, sync::Arc, time::Duration},
     tokio::net::UdpSocket,
     x25519_dalek::PublicKey,
-    zelana_core::{L2Transaction, SignedTransaction},
-    zelana_net::{protocol::Packet, EphemeralKeyPair, SessionKeys, KIND_SERVER_HELLO},
+    zelana_core::{L2Transaction, Query, QueryReply},
+    zelana_execution::ExecutionError,
+    zelana_net::{protocol::Packet, EphemeralKeyPair, SessionKeys, KIND_REPLY, KIND_SERVER_HELLO},
 };
 
 const MAX_DATAGRAM_SIZE: usize = 1500; // Standard MTU safe limit
+/// Pending deposits listed in one reply, so it fits in a datagram.
+const MAX_PENDING_REPLY: usize = 8;
+/// Where the database lives unless `SEQUENCER_DB` says otherwise.
+const DEFAULT_DB_PATH: &str = "./data/sequencer_db";
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     env_logger::init();
+
+    let db_path = env::var("SEQUENCER_DB").unwrap_or_else(|_| DEFAULT_DB_PATH.to_string());
+
+    // `zelana-sequencer <command> ...` inspects the database instead of running the node
+    let args: Vec<String> = env::args().collect();
+    if let Some(command) = args.get(1) {
+        let db = db::RocksDbStore::open_secondary(&db_path)?;
+        return inspect(&db, command, &args[2..]);
+    }
+
     info!("Sequencer  Starting...");
 
     //Bind UDP Socket
@@ -27,18 +46,40 @@ async fn main() -> anyhow::Result<()> {
 
     //Initialize State
     let sessions = Arc::new(SessionManager::new());
-    let executor = TransactionExecutor::new("./data/sequencer_db")?;
+    let chain_id = env::var("CHAIN_ID")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(1);
+    let executor = Arc::new(TransactionExecutor::new(
+        &db_path,
+        chain_id,
+        BatchConfig::from_env(),
+    )?);
 
-    let db_handle = executor.db.clone();
+    //Seal batches that reach their age limit
+    let sealer = executor.clone();
     tokio::spawn(async move {
-        let bridge_id = env::var("BRIDGE_PROGRAM_ID")
-            .unwrap_or_else(|_| "DouWDzYTAxi5c3ui695xqozJuP9SpAutDcTbyQnkAguo".to_string());
+        let mut ticker = tokio::time::interval(Duration::from_millis(500));
+        loop {
+            ticker.tick().await;
+            if let Err(e) = sealer.seal_if_due().await {
+                error!("Failed to seal batch: {}", e);
+            }
+        }
+    });
 
-        let wss_url =
-            env::var("SOLANA_WSS_URL").unwrap_or_else(|_| "ws://127.0.0.1:8900".to_string());
+    //Prove sealed batches in the background (PROVER_BACKEND, off by default)
+    if let Some(prover) = proving::backend_from_env()? {
+        let service = ProverService::new(executor.db.clone(), prover, ProverConfig::from_env()?)?;
+        tokio::spawn(service.run());
+        info!("Prover service started");
+    }
 
-        ingest::start_indexer(db_handle, wss_url, bridge_id).await;
-    });
+    //Index L1 deposits, resuming from the saved cursor after restarts and disconnects
+    tokio::spawn(ingest::supervise(
+        executor.clone(),
+        ingest::IndexerConfig::from_env()?,
+    ));
 
     let mut buf = [0u8; MAX_DATAGRAM_SIZE];
 
@@ -96,7 +137,7 @@ async fn main() -> anyhow::Result<()> {
                         //Handle Transaction
                         match handle_transaction(&plaintext, &executor).await {
                             Ok(_) => debug!("Tx Executed from {}", peer),
-                            Err(e) => warn!("Tx Failed from {}: {}", peer, e),
+                            Err(e) => warn!("Tx Failed from {} (code {}): {}", peer, e.code(), e),
                         }
                     }
                     Some(Err(e)) => {
@@ -111,8 +152,43 @@ async fn main() -> anyhow::Result<()> {
                 }
             }
 
-            Ok(Packet::ServerHello { .. }) => {
-                // Clients send ClientHello, not ServerHello. Ignore.
+            Ok(Packet::Query { nonce, ciphertext }) => {
+                let decrypted_opt =
+                    sessions.get_mut(&peer, |session| session.keys.decrypt(nonce, ciphertext));
+
+                match decrypted_opt {
+                    Some(Ok(plaintext)) => {
+                        let reply = match answer_query(&plaintext, &executor) {
+                            Ok(reply) => reply,
+                            Err(e) => {
+                                warn!("Query from {} failed: {}", peer, e);
+                                continue;
+                            }
+                        };
+                        let sealed =
+                            sessions.get_mut(&peer, |session| session.keys.encrypt_reply(&reply));
+
+                        if let Some(Ok(payload)) = sealed {
+                            let mut frame = Vec::with_capacity(1 + payload.len());
+                            frame.push(KIND_REPLY);
+                            frame.extend_from_slice(&payload);
+
+                            if let Err(e) = socket.send_to(&frame, peer).await {
+                                warn!("Failed to send Reply to {}: {}", peer, e);
+                            }
+                        }
+                    }
+                    Some(Err(e)) => {
+                        warn!("Decryption failed for {}: {}", peer, e);
+                    }
+                    None => {
+                        debug!("Unknown Peer {}, ignoring Query", peer);
+                    }
+                }
+            }
+
+            Ok(Packet::ServerHello { .. } | Packet::Reply { .. }) => {
+                // Only the server sends these. Ignore.
             }
 
             Err(e) => {
@@ -122,39 +198,178 @@ async fn main() -> anyhow::Result<()> {
     }
 }
 
-/// Decodes and routes the transaction to the executor
-async fn handle_transaction(
-    plaintext: &[u8],
-    executor: &TransactionExecutor,
-) -> anyhow::Result<()> {
-    //Deserialize
-    let tx: L2Transaction = wincode::deserialize(plaintext)?;
+/// Runs an inspection subcommand. `db` is a read-only secondary, so the sequencer can
+/// keep running.
+fn inspect(db: &db::RocksDbStore, command: &str, args: &[String]) -> anyhow::Result<()> {
+    match command {
+        // `export-batch <height> [out]` writes a sealed batch for the prover.
+        "export-batch" => {
+            let height: u64 = args
+                .first()
+                .ok_or_else(|| anyhow::anyhow!("usage: export-batch <height> [out]"))?
+                .parse()?;
+            let out = args.get(1).map(String::as_str).unwrap_or("batch.bin");
+            batch::export_batch(db, height, out)?;
+            info!("Exported batch {} to {}", height, out);
+        }
 
-    match tx {
-        L2Transaction::Transfer(signed_tx) => {
-            //Validate Signature (Anti-Spoofing)
-            // Even though ZK proves this later, we MUST check it now to protect the Sequencer.
-            verify_signature(&signed_tx)?;
+        // `exit-proof <height> <position> [out]` writes the proof the L1 bridge needs to
+        // release a queued withdrawal.
+        "exit-proof" => {
+            let usage = || anyhow::anyhow!("usage: exit-proof <height> <position> [out]");
+            let height: u64 = args.first().ok_or_else(usage)?.parse()?;
+            let position: u32 = args.get(1).ok_or_else(usage)?.parse()?;
+            let out = args.get(2).map(String::as_str).unwrap_or("exit-proof.bin");
+            let proof = batch::exit_proof(db, height, position)?;
+            std::fs::write(out, wincode::serialize(&proof)?)?;
+            info!(
+                "Wrote exit proof for batch {} #{} to {}",
+                height, position, out
+            );
+        }
+
+        // `withdrawals [from-height]` lists the queue the L1 bridge pays out.
+        "withdrawals" => {
+            let from_height: u64 = match args.first() {
+                Some(height) => height.parse()?,
+                None => 1,
+            };
+            for w in db.withdrawals(from_height)? {
+                println!(
+                    "batch {} #{} | {} -> L1 {} | Amt: {} | tx {}",
+                    w.batch_height,
+                    w.position,
+                    w.data.from.to_hex(),
+                    hex::encode(w.data.to_l1_address),
+                    w.data.amount,
+                    hex::encode(w.tx_hash)
+                );
+            }
+        }
+
+        // `deposits [from-seq]` lists the ledger of applied L1 deposits.
+        "deposits" => {
+            let from_seq: u64 = match args.first() {
+                Some(seq) => seq.parse()?,
+                None => 0,
+            };
+            for d in db.deposits(from_seq)? {
+                println!(
+                    "l1_seq {} | +{} for {} | batch {} | tx {}",
+                    d.event.l1_seq,
+                    d.event.amount,
+                    d.event.to.to_hex(),
+                    d.batch_height,
+                    hex::encode(d.tx_hash)
+                );
+            }
+        }
+
+        // `pending-deposits` lists L1 deposits seen but not credited yet.
+        "pending-deposits" => {
+            for d in db.pending_deposits()? {
+                println!(
+                    "l1_seq {} | +{} for {} | slot {} | tx {}",
+                    d.event.l1_seq,
+                    d.event.amount,
+                    d.event.to.to_hex(),
+                    d.slot,
+                    d.signature
+                );
+            }
+        }
 
-            //Execute
-            executor.process(signed_tx).await?;
+        // `proofs [from-height]` lists the proving status of sealed batches.
+        "proofs" => {
+            let from_height: u64 = match args.first() {
+                Some(height) => height.parse()?,
+                None => 1,
+            };
+            for record in db.proofs(from_height)? {
+                let status = match &record.status {
+                    ProofStatus::Queued => "queued".to_string(),
+                    ProofStatus::Proving => "proving".to_string(),
+                    ProofStatus::Proven => format!("proven ({} bytes)", record.proof.len()),
+                    ProofStatus::Failed(e) => format!("failed: {}", e),
+                };
+                println!(
+                    "batch {} | {} | attempts: {}",
+                    record.batch_height, status, record.attempts
+                );
+            }
         }
-        _ => {
-            // Handle Deposits/Withdrawals
+
+        // `aggregate <from> <to> [out]` folds the stored proofs of batches `from..=to`
+        // into one proof with the `PROVER_BACKEND` backend.
+        "aggregate" => {
+            let usage = || anyhow::anyhow!("usage: aggregate <from> <to> [out]");
+            let from: u64 = args.first().ok_or_else(usage)?.parse()?;
+            let to: u64 = args.get(1).ok_or_else(usage)?.parse()?;
+            let out = args
+                .get(2)
+                .map(String::as_str)
+                .unwrap_or("aggregate-proof.bin");
+            let prover = proving::backend_from_env()?
+                .ok_or_else(|| anyhow::anyhow!("PROVER_BACKEND is not set"))?;
+            let proof = proving::aggregate(db, prover.as_ref(), from, to)?;
+            std::fs::write(out, &proof.proof)?;
+            info!(
+                "Wrote aggregate proof of batches {}..={} to {} | root {}",
+                from,
+                to,
+                out,
+                hex::encode(proof.public_values.post_state_root)
+            );
         }
+
+        other => anyhow::bail!("Unknown command '{}'", other),
     }
     Ok(())
 }
 
-fn verify_signature(tx: &SignedTransaction) -> anyhow::Result<()> {
-    use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+/// Decodes a client's `Query` and returns the serialized `QueryReply`.
+fn answer_query(plaintext: &[u8], executor: &TransactionExecutor) -> anyhow::Result<Vec<u8>> {
+    let query: Query = wincode::deserialize(plaintext)?;
+    let reply = match query {
+        Query::PendingDeposits { account } => QueryReply::PendingDeposits(
+            executor
+                .db
+                .pending_deposits()?
+                .into_iter()
+                .filter(|deposit| deposit.event.to == account)
+                .take(MAX_PENDING_REPLY)
+                .collect(),
+        ),
+    };
+    Ok(wincode::serialize(&reply)?)
+}
 
-    let vk = VerifyingKey::from_bytes(&tx.signer_pubkey)?;
-    let sig = Signature::from_slice(&tx.signature)?;
+/// Decodes and routes the transaction to the executor
+async fn handle_transaction(
+    plaintext: &[u8],
+    executor: &TransactionExecutor,
+) -> Result<(), ExecutionError> {
+    //Deserialize
+    let tx: L2Transaction =
+        wincode::deserialize(plaintext).map_err(|_| ExecutionError::MalformedTransaction)?;
 
-    // Re-serialize data to verify (Must match SDK serialization exactly)
-    let msg = wincode::serialize(&tx.data)?;
+    match &tx {
+        L2Transaction::Transfer(signed_tx) => {
+            //Validate Signature and Ownership (Anti-Spoofing)
+            // The Guest rejects any batch holding an unauthorized transfer, so it must never get in.
+            signed_tx.verify()?;
+        }
+        L2Transaction::Withdraw(req) => {
+            // Same check as transfers: only the owner of `from` may burn its funds.
+            req.verify()?;
+        }
+        L2Transaction::Deposit(_) => {
+            // Deposits only come from the L1 indexer.
+            return Err(ExecutionError::Unsupported);
+        }
+    }
 
-    vk.verify(&msg, &sig)?;
+    //Execute
+    executor.process(tx).await?;
     Ok(())
 }
@@ -0,0 +1,614 @@
+use {
+    crate::db::RocksDbStore,
+    anyhow::{anyhow, bail, ensure, Context, Result},
+    log::{error, info, warn},
+    std::{
+        collections::HashMap,
+        env,
+        panic::{catch_unwind, AssertUnwindSafe},
+        sync::Arc,
+        time::Duration,
+    },
+    tokio::{task::JoinSet, time::Instant},
+    wincode::{SchemaRead, SchemaWrite},
+    zelana_core::BatchPublicValues,
+    zelana_prover::{AggregateProof, BatchProof, NativeProver, Prover},
+};
+
+/// Where a sealed batch is in the proving pipeline.
+#[derive(Debug, Clone, PartialEq, Eq, SchemaRead, SchemaWrite)]
+pub enum ProofStatus {
+    Queued,
+    Proving,
+    Proven,
+    /// Out of attempts; holds the last error.
+    Failed(String),
+}
+
+impl ProofStatus {
+    /// Whether the pipeline is done with the batch.
+    pub fn is_final(&self) -> bool {
+        matches!(self, ProofStatus::Proven | ProofStatus::Failed(_))
+    }
+}
+
+/// The proof of a sealed batch, as persisted in the `proofs` column family.
+#[derive(Debug, Clone, SchemaRead, SchemaWrite)]
+pub struct ProofRecord {
+    pub batch_height: u64,
+    pub status: ProofStatus,
+    /// Proving attempts started so far.
+    pub attempts: u32,
+    /// What the proof commits to. Set once proven.
+    pub public_values: Option<BatchPublicValues>,
+    /// The backend's serialized proof. Empty until proven.
+    pub proof: Vec<u8>,
+}
+
+impl ProofRecord {
+    fn queued(batch_height: u64) -> Self {
+        Self {
+            batch_height,
+            status: ProofStatus::Queued,
+            attempts: 0,
+            public_values: None,
+            proof: Vec::new(),
+        }
+    }
+}
+
+/// How the prover service schedules jobs.
+#[derive(Debug, Clone)]
+pub struct ProverConfig {
+    /// Batches proven at the same time.
+    pub concurrency: usize,
+    /// Attempts per batch before it is marked failed.
+    pub max_attempts: u32,
+    /// How long a failed batch waits before its next attempt.
+    pub retry_delay: Duration,
+    /// How long to wait when there is nothing to prove.
+    pub poll_interval: Duration,
+}
+
+impl Default for ProverConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: 1,
+            max_attempts: 3,
+            retry_delay: Duration::from_secs(10),
+            poll_interval: Duration::from_secs(1),
+        }
+    }
+}
+
+impl ProverConfig {
+    /// Reads `PROVER_CONCURRENCY`, `PROVER_MAX_ATTEMPTS`, `PROVER_RETRY_MS` and
+    /// `PROVER_POLL_MS`, falling back to the defaults.
+    pub fn from_env() -> Result<Self> {
+        let default = Self::default();
+        let config = Self {
+            concurrency: env::var("PROVER_CONCURRENCY")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.concurrency),
+            max_attempts: env::var("PROVER_MAX_ATTEMPTS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.max_attempts),
+            retry_delay: env::var("PROVER_RETRY_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .map(Duration::from_millis)
+                .unwrap_or(default.retry_delay),
+            poll_interval: env::var("PROVER_POLL_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .map(Duration::from_millis)
+                .unwrap_or(default.poll_interval),
+        };
+        ensure!(
+            config.concurrency > 0,
+            "PROVER_CONCURRENCY must be at least 1"
+        );
+        Ok(config)
+    }
+}
+
+/// The backend named by `PROVER_BACKEND`, or `None` if proving is disabled.
+///
+/// `native` runs the Guest logic on the host and produces no real proof (for CI).
+/// `sp1` needs the `sp1` feature and proves with the Guest ELF at `PROVER_ELF`; set
+/// `PROVER_AGGREGATOR_ELF` to the aggregation Guest to aggregate its proofs.
+pub fn backend_from_env() -> Result<Option<Arc<dyn Prover>>> {
+    let Ok(backend) = env::var("PROVER_BACKEND") else {
+        return Ok(None);
+    };
+    match backend.as_str() {
+        "native" => Ok(Some(Arc::new(NativeProver))),
+        #[cfg(feature = "sp1")]
+        "sp1" => {
+            let path = env::var("PROVER_ELF").context("PROVER_ELF is not set")?;
+            let elf =
+                std::fs::read(&path).with_context(|| format!("Failed to read ELF {}", path))?;
+            let mut prover = zelana_prover::Sp1Prover::new(&elf).compressed();
+            if let Ok(path) = env::var("PROVER_AGGREGATOR_ELF") {
+                let elf =
+                    std::fs::read(&path).with_context(|| format!("Failed to read ELF {}", path))?;
+                prover = prover.with_aggregator(&elf);
+            }
+            Ok(Some(Arc::new(prover)))
+        }
+        other => bail!("Unknown PROVER_BACKEND '{}'", other),
+    }
+}
+
+/// Proves sealed batches and keeps a `ProofRecord` for each one.
+///
+/// Every sealed batch is queued once, in height order. Up to `concurrency` queued
+/// batches are proven at a time, and a free slot is refilled as soon as a job
+/// finishes. A failed attempt puts the batch back in the queue, to be retried after
+/// `retry_delay`, until it has used up `max_attempts`.
+pub struct ProverService {
+    db: RocksDbStore,
+    prover: Arc<dyn Prover>,
+    config: ProverConfig,
+    /// Every batch below this height has a final record.
+    cursor: u64,
+    /// The attempts in flight. Each returns the record it was started with.
+    jobs: JoinSet<(ProofRecord, Result<BatchProof>)>,
+    /// When each batch with a failed attempt may be retried.
+    retry_at: HashMap<u64, Instant>,
+}
+
+impl ProverService {
+    /// Batches left `Proving` by a previous run are queued again.
+    pub fn new(db: RocksDbStore, prover: Arc<dyn Prover>, config: ProverConfig) -> Result<Self> {
+        let mut write = db.write();
+        for mut record in db.proofs(1)? {
+            if record.status == ProofStatus::Proving {
+                record.status = ProofStatus::Queued;
+                write.put_proof(&record)?;
+            }
+        }
+        write.commit()?;
+
+        Ok(Self {
+            db,
+            prover,
+            config,
+            cursor: 1,
+            jobs: JoinSet::new(),
+            retry_at: HashMap::new(),
+        })
+    }
+
+    pub async fn run(mut self) {
+        loop {
+            match self.run_once().await {
+                // With jobs in flight, `run_once` has already waited
+                Ok(0) if self.jobs.is_empty() => {
+                    tokio::time::sleep(self.config.poll_interval).await
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    error!("Prover service error: {}", e);
+                    tokio::time::sleep(self.config.poll_interval).await;
+                }
+            }
+        }
+    }
+
+    /// Queues newly sealed batches and fills the free slots with queued ones that
+    /// are due. Then waits up to `poll_interval` for jobs to finish and records them.
+    /// Returns how many jobs finished.
+    pub async fn run_once(&mut self) -> Result<usize> {
+        self.start_jobs()?;
+        if self.jobs.is_empty() {
+            return Ok(0);
+        }
+
+        let mut finished = Vec::new();
+        if let Ok(Some(job)) =
+            tokio::time::timeout(self.config.poll_interval, self.jobs.join_next()).await
+        {
+            finished.push(job);
+        }
+        while let Some(job) = self.jobs.try_join_next() {
+            finished.push(job);
+        }
+
+        let count = finished.len();
+        let mut write = self.db.write();
+        for job in finished {
+            // Attempts never panic (see `start_jobs`) and are never aborted
+            let (mut record, result) = job.context("Proving task failed")?;
+            match result {
+                Ok(proof) => {
+                    info!(
+                        "PROVEN: batch {} | root {}",
+                        record.batch_height,
+                        hex::encode(proof.public_values.post_state_root)
+                    );
+                    record.status = ProofStatus::Proven;
+                    record.public_values = Some(proof.public_values);
+                    record.proof = proof.proof;
+                }
+                Err(e) if record.attempts < self.config.max_attempts => {
+                    warn!(
+                        "Proving batch {} failed (attempt {}): {:#}",
+                        record.batch_height, record.attempts, e
+                    );
+                    record.status = ProofStatus::Queued;
+                    self.retry_at.insert(
+                        record.batch_height,
+                        Instant::now() + self.config.retry_delay,
+                    );
+                }
+                Err(e) => {
+                    error!(
+                        "Giving up on batch {} after {} attempts: {:#}",
+                        record.batch_height, record.attempts, e
+                    );
+                    record.status = ProofStatus::Failed(format!("{:#}", e));
+                }
+            }
+            write.put_proof(&record)?;
+        }
+        write.commit()?;
+
+        for record in self.db.proofs(self.cursor)? {
+            if !record.status.is_final() {
+                break;
+            }
+            self.cursor = record.batch_height + 1;
+        }
+        Ok(count)
+    }
+
+    /// Queues newly sealed batches and starts attempts on due ones while there are
+    /// free slots.
+    fn start_jobs(&mut self) -> Result<()> {
+        let mut records = self.db.proofs(self.cursor)?;
+        let mut write = self.db.write();
+        let next_height = self.cursor + records.len() as u64;
+        for height in next_height..=self.db.batch_height()? {
+            let record = ProofRecord::queued(height);
+            write.put_proof(&record)?;
+            records.push(record);
+        }
+
+        let now = Instant::now();
+        let free = self.config.concurrency.saturating_sub(self.jobs.len());
+        let mut due: Vec<_> = records
+            .into_iter()
+            .filter(|record| {
+                record.status == ProofStatus::Queued
+                    && self
+                        .retry_at
+                        .get(&record.batch_height)
+                        .is_none_or(|at| *at <= now)
+            })
+            .take(free)
+            .collect();
+        for record in &mut due {
+            record.status = ProofStatus::Proving;
+            record.attempts += 1;
+            write.put_proof(record)?;
+        }
+        write.commit()?;
+
+        for record in due {
+            self.retry_at.remove(&record.batch_height);
+            let db = self.db.clone();
+            let prover = self.prover.clone();
+            self.jobs.spawn_blocking(move || {
+                let height = record.batch_height;
+                // A panicking backend counts as a failed attempt
+                let result = catch_unwind(AssertUnwindSafe(|| prove(&db, prover.as_ref(), height)))
+                    .unwrap_or_else(|_| Err(anyhow!("Prover panicked on batch {}", height)));
+                (record, result)
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Proves the sealed batch at `height` and checks the proof is for that batch.
+fn prove(db: &RocksDbStore, prover: &dyn Prover, height: u64) -> Result<BatchProof> {
+    let batch = db
+        .get_batch(height)?
+        .with_context(|| format!("Batch {} not found", height))?;
+
+    let proof = prover.prove(&batch.input)?;
+    let values = &proof.public_values;
+    ensure!(
+        values.batch_height == height
+            && values.chain_id == batch.input.chain_id
+            && values.pre_state_root == batch.header.pre_state_root
+            && values.post_state_root == batch.header.post_state_root
+            && values.transactions_root == batch.header.transactions_root
+            && values.withdrawals_root == batch.header.withdrawals_root,
+        "Proof of batch {} does not match its header",
+        height
+    );
+    Ok(proof)
+}
+
+/// Aggregates the stored proofs of batches `from..=to`, which must all be proven.
+pub fn aggregate(
+    db: &RocksDbStore,
+    prover: &dyn Prover,
+    from: u64,
+    to: u64,
+) -> Result<AggregateProof> {
+    let mut proofs = Vec::new();
+    for record in db.proofs(from)? {
+        if record.batch_height > to {
+            break;
+        }
+        let (ProofStatus::Proven, Some(public_values)) = (&record.status, record.public_values)
+        else {
+            bail!("Batch {} is not proven", record.batch_height);
+        };
+        proofs.push(BatchProof {
+            public_values,
+            proof: record.proof,
+        });
+    }
+    ensure!(
+        proofs.len() as u64 == (to + 1).saturating_sub(from),
+        "Not every batch in {}..={} has a proof record",
+        from,
+        to
+    );
+    prover.aggregate(&proofs)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::batch::{BatchBuilder, BatchConfig},
+        ed25519_dalek::SigningKey,
+        std::sync::atomic::{AtomicBool, AtomicU32, Ordering},
+        zelana_core::{
+            test_utils::{signed_transfer, temp_path},
+            AccountId, BatchInput,
+        },
+        zelana_execution::{AccountState, StateStore},
+    };
+
+    /// Fails its first `failures` calls, then proves natively.
+    struct FlakyProver {
+        failures: u32,
+        calls: AtomicU32,
+    }
+
+    impl Prover for FlakyProver {
+        fn prove(&self, input: &BatchInput) -> Result<BatchProof> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+                bail!("prover unavailable");
+            }
+            NativeProver.prove(input)
+        }
+
+        fn aggregate(&self, proofs: &[BatchProof]) -> Result<AggregateProof> {
+            NativeProver.aggregate(proofs)
+        }
+    }
+
+    /// Proves natively, then claims a different set of transactions.
+    struct ForgingProver;
+
+    impl Prover for ForgingProver {
+        fn prove(&self, input: &BatchInput) -> Result<BatchProof> {
+            let mut proof = NativeProver.prove(input)?;
+            proof.public_values.transactions_root = [0xff; 32];
+            Ok(proof)
+        }
+
+        fn aggregate(&self, proofs: &[BatchProof]) -> Result<AggregateProof> {
+            NativeProver.aggregate(proofs)
+        }
+    }
+
+    // With the clock paused, timers only fire once no proving job is running
+    #[tokio::test(start_paused = true)]
+    async fn test_sealed_batches_are_proven_with_retries() {
+        let path = temp_path("proofs");
+
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let alice = AccountId(key.verifying_key().to_bytes());
+        let mut db = RocksDbStore::open(&path).unwrap();
+        db.set_account(
+            alice,
+            AccountState {
+                balance: 100,
+                nonce: 0,
+            },
+        )
+        .unwrap();
+
+        let config = BatchConfig {
+            max_txs: 1,
+            max_age: Duration::from_secs(3600),
+        };
+        let mut builder = BatchBuilder::new(db.clone(), 1, config);
+        builder
+            .execute(signed_transfer(&key, AccountId([2u8; 32]), 10, 0))
+            .unwrap();
+        builder
+            .execute(signed_transfer(&key, AccountId([2u8; 32]), 10, 1))
+            .unwrap();
+
+        let prover = Arc::new(FlakyProver {
+            failures: 3,
+            calls: AtomicU32::new(0),
+        });
+        let config = ProverConfig {
+            concurrency: 1,
+            max_attempts: 2,
+            retry_delay: Duration::from_secs(60),
+            poll_interval: Duration::from_secs(3600),
+        };
+        let mut service = ProverService::new(db.clone(), prover, config).unwrap();
+        let status = |db: &RocksDbStore| -> Vec<(ProofStatus, u32)> {
+            db.proofs(1)
+                .unwrap()
+                .into_iter()
+                .map(|r| (r.status, r.attempts))
+                .collect()
+        };
+
+        // A failed attempt goes back to the queue...
+        assert_eq!(service.run_once().await.unwrap(), 1);
+        assert_eq!(
+            status(&db),
+            vec![(ProofStatus::Queued, 1), (ProofStatus::Queued, 0)]
+        );
+        // ...behind the batches that are due
+        assert_eq!(service.run_once().await.unwrap(), 1);
+        assert_eq!(
+            status(&db),
+            vec![(ProofStatus::Queued, 1), (ProofStatus::Queued, 1)]
+        );
+        assert_eq!(service.run_once().await.unwrap(), 0);
+
+        // ...until the batch is out of attempts
+        tokio::time::advance(Duration::from_secs(60)).await;
+        assert_eq!(service.run_once().await.unwrap(), 1);
+        assert_eq!(
+            status(&db)[0],
+            (ProofStatus::Failed("prover unavailable".to_string()), 2)
+        );
+        assert_eq!(service.run_once().await.unwrap(), 1);
+        assert_eq!(status(&db)[1], (ProofStatus::Proven, 2));
+        assert_eq!(service.run_once().await.unwrap(), 0);
+
+        let record = db.proofs(2).unwrap().remove(0);
+        let batch = db.get_batch(2).unwrap().unwrap();
+        assert_eq!(
+            record.public_values.unwrap().post_state_root,
+            batch.header.post_state_root
+        );
+
+        // Batches sealed later are picked up
+        builder
+            .execute(signed_transfer(&key, AccountId([2u8; 32]), 10, 2))
+            .unwrap();
+        assert_eq!(service.run_once().await.unwrap(), 1);
+        assert_eq!(status(&db)[2], (ProofStatus::Proven, 1));
+
+        // Stored proofs aggregate, as long as every batch in the range is proven
+        let combined = aggregate(&db, &NativeProver, 2, 3).unwrap();
+        assert_eq!(
+            combined.public_values.pre_state_root,
+            batch.header.pre_state_root
+        );
+        assert_eq!(
+            combined.public_values.post_state_root,
+            db.state_root().unwrap()
+        );
+        assert!(aggregate(&db, &NativeProver, 1, 3).is_err());
+        assert!(aggregate(&db, &NativeProver, 2, 4).is_err());
+
+        // A proof is only kept if it commits to the batch's own transactions
+        assert!(prove(&db, &NativeProver, 2).is_ok());
+        assert!(prove(&db, &ForgingProver, 2).is_err());
+
+        drop(service);
+        drop(builder);
+        drop(db);
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    /// Holds the proof of batch 1 until released.
+    struct SlowProver {
+        released: AtomicBool,
+    }
+
+    impl Prover for SlowProver {
+        fn prove(&self, input: &BatchInput) -> Result<BatchProof> {
+            while input.batch_height == 1 && !self.released.load(Ordering::SeqCst) {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            NativeProver.prove(input)
+        }
+
+        fn aggregate(&self, proofs: &[BatchProof]) -> Result<AggregateProof> {
+            NativeProver.aggregate(proofs)
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_slow_proof_does_not_hold_up_the_others() {
+        let path = temp_path("slow-proofs");
+
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let mut db = RocksDbStore::open(&path).unwrap();
+        db.set_account(
+            AccountId(key.verifying_key().to_bytes()),
+            AccountState {
+                balance: 100,
+                nonce: 0,
+            },
+        )
+        .unwrap();
+        let config = BatchConfig {
+            max_txs: 1,
+            max_age: Duration::from_secs(3600),
+        };
+        let mut builder = BatchBuilder::new(db.clone(), 1, config);
+        for nonce in 0..3 {
+            builder
+                .execute(signed_transfer(&key, AccountId([2u8; 32]), 10, nonce))
+                .unwrap();
+        }
+
+        let prover = Arc::new(SlowProver {
+            released: AtomicBool::new(false),
+        });
+        let config = ProverConfig {
+            concurrency: 2,
+            poll_interval: Duration::from_secs(5),
+            ..ProverConfig::default()
+        };
+        let mut service = ProverService::new(db.clone(), prover.clone(), config).unwrap();
+        let status = |db: &RocksDbStore| -> Vec<ProofStatus> {
+            db.proofs(1)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.status)
+                .collect()
+        };
+
+        // The slot batch 2 frees goes to batch 3 while batch 1 is still proving
+        assert_eq!(service.run_once().await.unwrap(), 1);
+        assert_eq!(
+            status(&db),
+            vec![
+                ProofStatus::Proving,
+                ProofStatus::Proven,
+                ProofStatus::Queued
+            ]
+        );
+        assert_eq!(service.run_once().await.unwrap(), 1);
+        assert_eq!(
+            status(&db),
+            vec![
+                ProofStatus::Proving,
+                ProofStatus::Proven,
+                ProofStatus::Proven
+            ]
+        );
+
+        prover.released.store(true, Ordering::SeqCst);
+        assert_eq!(service.run_once().await.unwrap(), 1);
+        assert_eq!(status(&db), vec![ProofStatus::Proven; 3]);
+
+        drop(service);
+        drop(builder);
+        drop(db);
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+}
@@ -7,7 +7,11 @@ use {
         io::{self, Read, Write},
         path::PathBuf,
     },
-    zelana_core::{prover::BatchInput, L2Transaction, SignedTransaction},
+    zelana_core::{
+        merkle::{MultiProof, MultiProofNode},
+        prover::BatchInput,
+        DepositEvent, L2Transaction, SignedTransaction, WithdrawRequest,
+    },
 };
 
 /// Convert a wincode-serialized BatchInput (batch.bin) into JSON.
@@ -43,6 +47,31 @@ fn signed_tx_to_json(signed: &SignedTransaction) -> Value {
         },
         "signature" : hex::encode(&signed.signature),
         "signer_pubkey" : hex::encode(signed.signer_pubkey),
+        "privacy_pubkey" : signed.privacy_pubkey.map(hex::encode),
+    })
+}
+
+fn withdraw_to_json(req: &WithdrawRequest) -> Value {
+    let d = &req.data;
+    json!({
+        "data": {
+            "from" : hex::encode(d.from),
+            "to_l1_address" : hex::encode(d.to_l1_address),
+            "amount" : d.amount,
+            "nonce" : d.nonce,
+            "chain_id" : d.chain_id,
+        },
+        "signature" : hex::encode(&req.signature),
+        "signer_pubkey" : hex::encode(req.signer_pubkey),
+        "privacy_pubkey" : req.privacy_pubkey.map(hex::encode),
+    })
+}
+
+fn deposit_to_json(deposit: &DepositEvent) -> Value {
+    json!({
+        "to" : hex::encode(deposit.to),
+        "amount" : deposit.amount,
+        "l1_seq" : deposit.l1_seq,
     })
 }
 
@@ -51,10 +80,33 @@ fn l2tx_to_json(tx: &L2Transaction) -> Value {
         L2Transaction::Transfer(signed) => json!({
             "Transfer": signed_tx_to_json(signed)
         }),
-        other => Value::String(format!("{:?}", other)),
+        L2Transaction::Withdraw(req) => json!({
+            "Withdraw": withdraw_to_json(req)
+        }),
+        L2Transaction::Deposit(deposit) => json!({
+            "Deposit": deposit_to_json(deposit)
+        }),
     }
 }
 
+fn multiproof_to_json(proof: &MultiProof) -> Value {
+    let nodes: Vec<Value> = proof
+        .nodes
+        .iter()
+        .map(|node| match node {
+            MultiProofNode::Empty => json!("Empty"),
+            MultiProofNode::Leaf(id, data) => json!({
+                "Leaf": { "id": hex::encode(id), "balance": data.balance, "nonce": data.nonce }
+            }),
+            MultiProofNode::Pruned(left, right) => json!({
+                "Pruned": { "left": hex::encode(left), "right": hex::encode(right) }
+            }),
+            MultiProofNode::Branch => json!("Branch"),
+        })
+        .collect();
+    Value::Array(nodes)
+}
+
 fn read_all_input(input: &str) -> anyhow::Result<Vec<u8>> {
     if input == "-" {
         let mut buf = Vec::new();
@@ -87,9 +139,13 @@ fn write_output(output: &Option<PathBuf>, s: &str) -> anyhow::Result<()> {
 
 fn batch_to_json_string(batch: &BatchInput, indent: usize) -> String {
     let mut root = json!({
+        "batch_height": batch.batch_height,
+        "chain_id": batch.chain_id,
+        "prev_l1_seq": batch.prev_l1_seq,
         "pre_state_root": hex::encode(batch.pre_state_root),
         "transactions": [],
-        "witness_accounts": {}
+        "witness_accounts": {},
+        "witness_proof": multiproof_to_json(&batch.witness_proof),
     });
 
     // Push transactions
@@ -104,12 +160,12 @@ fn batch_to_json_string(batch: &BatchInput, indent: usize) -> String {
         .get_mut("witness_accounts")
         .and_then(|v| v.as_object_mut())
     {
-        for (aid, acct) in batch.witness_accounts.iter() {
+        for acct in batch.witness_accounts.iter() {
             map.insert(
-                hex::encode(aid),
+                hex::encode(acct.id),
                 json!({
-                    "balance": acct.balance,
-                    "nonce": acct.nonce,
+                    "balance": acct.data.balance,
+                    "nonce": acct.data.nonce,
                 }),
             );
         }